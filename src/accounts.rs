@@ -0,0 +1,452 @@
+use crate::clearing::{Allocation, ClearedTrade, Clearing};
+use crate::fees::{BrokerVolumes, FeeSchedules};
+use crate::market_data::{split_shares, ExecutionReport, SettlementReport};
+use crate::order_book::{IncomingOrder, RestingOrder};
+use crate::stock_data::Stock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+// Default location of the stock loan terms
+pub const SHORT_SELLING_PATH: &str = "config/short_selling.json";
+
+// Terms on which clients can borrow shares to sell short
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ShortSellingConfig {
+    pub borrow_fraction: f64, // Share of each symbol's availability that can be lent out
+    pub borrow_fee_rate: f64, // Annual fee on the value of borrowed shares, charged every session
+}
+
+impl Default for ShortSellingConfig {
+    fn default() -> Self {
+        Self {
+            borrow_fraction: 0.2,
+            borrow_fee_rate: 0.03,
+        }
+    }
+}
+
+impl ShortSellingConfig {
+    // Load the terms from disk, falling back to the defaults
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                println!("[Short Selling] Invalid config {}: {}, using defaults", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+// Cash and share positions of one client, built up from its fills
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Account {
+    pub cash: f64,
+    pub positions: BTreeMap<String, i64>, // Long shares held per symbol
+    #[serde(default)]
+    pub short_positions: BTreeMap<String, u32>, // Borrowed shares sold short per symbol
+    #[serde(default)]
+    pub fees_paid: f64, // Commissions net of rebates
+    #[serde(default)]
+    pub unsettled_cash: f64, // Part of `cash` still to be received (+) or paid (-) on settlement
+    #[serde(default)]
+    pub unsettled_positions: BTreeMap<String, i64>, // Part of `positions` still to be received or delivered
+    #[serde(default)]
+    committed: BTreeMap<String, u32>, // Long shares promised to open sell orders
+    #[serde(default)]
+    located: BTreeMap<String, u32>, // Borrow located for open short sales
+    #[serde(default)]
+    covering: BTreeMap<String, u32>, // Short shares promised to open buy-to-cover orders
+}
+
+// Ledger of every client account in the stock system, plus the shares available to borrow
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Accounts {
+    accounts: BTreeMap<u32, Account>,
+    #[serde(default)]
+    borrow_pool: BTreeMap<String, u32>,
+    #[serde(default)]
+    broker_volumes: BrokerVolumes,
+    #[serde(default)]
+    clearing: Clearing,
+    #[serde(skip)]
+    fee_schedules: FeeSchedules,
+    #[serde(skip)]
+    reports: Vec<ExecutionReport>,
+}
+
+impl Accounts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Lendable shares for symbols that have none yet, e.g. a new ledger or a newly listed symbol
+    pub fn seed_borrow_pool(&mut self, stocks: &[Stock], fraction: f64) {
+        for stock in stocks {
+            self.borrow_pool
+                .entry(stock.name.clone())
+                .or_insert((stock.availability as f64 * fraction) as u32);
+        }
+    }
+
+    pub fn set_fee_schedules(&mut self, fee_schedules: FeeSchedules) {
+        self.fee_schedules = fee_schedules;
+    }
+
+    // Volume tiers are per calendar month
+    pub fn start_day(&mut self, date: &str) {
+        self.broker_volumes.start_day(date);
+    }
+
+    // Check an order against the client's holdings and the borrow pool and set aside what it needs:
+    // long sales need unencumbered shares, short sales a locate and buy-to-cover an open short.
+    pub fn accept(&mut self, order: &IncomingOrder) -> Result<(), String> {
+        let account = self.accounts.entry(order.client_id).or_default();
+        let (stock, quantity) = (&order.stock, order.quantity);
+
+        let result = match order.action.as_str() {
+            "Buy" => Ok(()),
+            "Sell" => {
+                let held = account.positions.get(stock).copied().unwrap_or(0).max(0) as u64;
+                let free = held.saturating_sub(get(&account.committed, stock) as u64);
+                if quantity as u64 <= free {
+                    add(&mut account.committed, stock, quantity);
+                    Ok(())
+                } else {
+                    Err(format!("long sale of {} exceeds the {} unencumbered shares held", quantity, free))
+                }
+            }
+            "SellShort" => {
+                let available = get(&self.borrow_pool, stock);
+                if quantity <= available {
+                    take(&mut self.borrow_pool, stock, quantity);
+                    add(&mut account.located, stock, quantity);
+                    Ok(())
+                } else {
+                    Err(format!("locate failed: {} shares requested, {} available to borrow", quantity, available))
+                }
+            }
+            "BuyToCover" => {
+                let free = get(&account.short_positions, stock).saturating_sub(get(&account.covering, stock));
+                if quantity <= free {
+                    add(&mut account.covering, stock, quantity);
+                    Ok(())
+                } else {
+                    Err(format!("buy-to-cover of {} exceeds the {} shares short", quantity, free))
+                }
+            }
+            other => Err(format!("unknown action {}", other)),
+        };
+
+        match &result {
+            Ok(()) => self.report(stock, &order.resting(quantity), "Accepted", order.price, None),
+            Err(reason) => self.reject(order, reason),
+        }
+        result
+    }
+
+    // Report an order that was turned away before anything was set aside for it
+    pub fn reject(&mut self, order: &IncomingOrder, reason: &str) {
+        self.report(&order.stock, &order.resting(order.quantity), "Rejected", 0.0, Some(reason.to_string()));
+    }
+
+    // Apply one side of a fill to the client's cash and positions, using up what was set aside for it,
+    // and charge the broker's fee. `liquidity` is "Maker", "Taker" or "Auction".
+    pub fn record_fill(&mut self, stock: &str, fill: &RestingOrder, price: f64, liquidity: &str) {
+        let account = self.accounts.entry(fill.client_id).or_default();
+        let quantity = fill.quantity;
+
+        let monthly_volume = self.broker_volumes.get(fill.broker_id);
+        let fee = self.fee_schedules.for_broker(fill.broker_id).fee(quantity, price, monthly_volume, liquidity);
+        self.broker_volumes.add(fill.broker_id, quantity);
+        account.cash -= fee;
+        account.fees_paid += fee;
+
+        // Cash and own shares change hands on the settlement date; borrowed shares never were the client's
+        let value = quantity as f64 * price;
+        let (shares, long_shares, cash) = match fill.action.as_str() {
+            "Buy" => (quantity as i64, quantity as i64, -value),
+            "Sell" => (-(quantity as i64), -(quantity as i64), value),
+            "SellShort" => (-(quantity as i64), 0, value),
+            _ => (quantity as i64, 0, -value),
+        };
+        account.unsettled_cash += cash - fee;
+        add_signed(&mut account.unsettled_positions, stock, long_shares);
+        self.clearing.record(ClearedTrade {
+            client_id: fill.client_id,
+            broker_id: fill.broker_id,
+            stock: stock.to_string(),
+            shares,
+            long_shares,
+            cash: cash - fee,
+        });
+
+        match fill.action.as_str() {
+            "Buy" => {
+                account.cash -= quantity as f64 * price;
+                *account.positions.entry(stock.to_string()).or_insert(0) += quantity as i64;
+            }
+            "Sell" => {
+                account.cash += quantity as f64 * price;
+                take(&mut account.committed, stock, quantity);
+                let position = account.positions.entry(stock.to_string()).or_insert(0);
+                *position -= quantity as i64;
+                if *position == 0 {
+                    account.positions.remove(stock);
+                }
+            }
+            "SellShort" => {
+                account.cash += quantity as f64 * price;
+                take(&mut account.located, stock, quantity);
+                add(&mut account.short_positions, stock, quantity);
+            }
+            "BuyToCover" => {
+                account.cash -= quantity as f64 * price;
+                take(&mut account.covering, stock, quantity);
+                take(&mut account.short_positions, stock, quantity);
+                // Covered shares go back to the lender
+                add(&mut self.borrow_pool, stock, quantity);
+            }
+            _ => {}
+        }
+
+        let mut report = execution_report(stock, fill, "Filled", price, None);
+        report.fee = fee;
+        report.liquidity = Some(liquidity.to_string());
+        self.reports.push(report);
+    }
+
+    // Give back what was set aside for the unexecuted part of an order that will never fill
+    pub fn release(&mut self, stock: &str, order: &RestingOrder, status: &str, reason: &str) {
+        let account = self.accounts.entry(order.client_id).or_default();
+        match order.action.as_str() {
+            "Sell" => take(&mut account.committed, stock, order.quantity),
+            "SellShort" => {
+                take(&mut account.located, stock, order.quantity);
+                add(&mut self.borrow_pool, stock, order.quantity);
+            }
+            "BuyToCover" => take(&mut account.covering, stock, order.quantity),
+            _ => {}
+        }
+
+        self.report(stock, order, status, 0.0, Some(reason.to_string()));
+    }
+
+    // Net the day's trades into settlement obligations due on `settlement_date`
+    pub fn close_trading_day(&mut self, trade_date: &str, settlement_date: &str) -> Vec<SettlementReport> {
+        self.clearing.net_trades(trade_date, settlement_date)
+    }
+
+    // Settle every obligation due by `date`. Receipts settle first, so shares bought earlier can be
+    // delivered; a seller without enough settled shares fails and is retried on the next date.
+    pub fn settle(&mut self, date: &str) -> Vec<SettlementReport> {
+        let mut due = self.clearing.take_due(date);
+        let mut failed: Vec<BTreeMap<u32, Allocation>> = vec![BTreeMap::new(); due.len()];
+
+        for receipts in [true, false] {
+            for (obligation, failed) in due.iter().zip(failed.iter_mut()) {
+                for (client_id, allocation) in obligation.allocations.iter() {
+                    if (allocation.long_shares >= 0) != receipts {
+                        continue;
+                    }
+                    let account = self.accounts.entry(*client_id).or_default();
+                    let held = account.positions.get(&obligation.stock).copied().unwrap_or(0);
+                    let unsettled = account.unsettled_positions.get(&obligation.stock).copied().unwrap_or(0);
+                    if held - unsettled + allocation.long_shares < 0 {
+                        println!(
+                            "[Settlement Fail] Client: {}, Broker: {}, Stock: {}, Shares Due: {}, Settled Shares Held: {}",
+                            client_id,
+                            obligation.broker_id,
+                            obligation.stock,
+                            -allocation.long_shares,
+                            held - unsettled
+                        );
+                        failed.insert(*client_id, allocation.clone());
+                        continue;
+                    }
+                    account.unsettled_cash -= allocation.cash;
+                    add_signed(&mut account.unsettled_positions, &obligation.stock, -allocation.long_shares);
+                }
+            }
+        }
+
+        let mut reports = Vec::new();
+        for (mut obligation, failed) in due.drain(..).zip(failed) {
+            if failed.is_empty() {
+                reports.push(obligation.report("Settled", 0));
+                continue;
+            }
+            let failed_shares = failed.values().map(|a| a.long_shares.unsigned_abs()).sum();
+            reports.push(obligation.report("Failed", failed_shares));
+            obligation.allocations = failed;
+            self.clearing.carry_over(obligation);
+        }
+        reports
+    }
+
+    // Charge one session's borrow fee on every short position, at the closing price
+    pub fn charge_borrow_fees(&mut self, stocks: &[Stock], annual_rate: f64) {
+        for (client_id, account) in self.accounts.iter_mut() {
+            for (stock_name, shares) in account.short_positions.iter() {
+                let Some(stock) = stocks.iter().find(|s| &s.name == stock_name) else { continue };
+                let fee = *shares as f64 * stock.price * annual_rate / 252.0;
+                account.cash -= fee;
+                println!(
+                    "[Borrow Fee] Client: {}, Stock: {}, Shares Borrowed: {}, Fee: {:.2}",
+                    client_id, stock_name, shares, fee
+                );
+            }
+        }
+    }
+
+    // Pay a cash dividend to holders of the symbol; short sellers pay it to the lender instead.
+    // Returns the net amount paid out.
+    pub fn apply_dividend(&mut self, stock: &str, amount: f64) -> f64 {
+        let mut paid = 0.0;
+        for account in self.accounts.values_mut() {
+            let long = account.positions.get(stock).copied().unwrap_or(0) as f64;
+            let short = get(&account.short_positions, stock) as f64;
+            let payment = (long - short) * amount;
+            account.cash += payment;
+            paid += payment;
+        }
+        paid
+    }
+
+    // Whether every share count kept for the symbol still fits once scaled by the split
+    pub fn split_fits(&self, stock: &str, new_shares: u32, old_shares: u32) -> bool {
+        let fits =
+            |shares: Option<&u32>| shares.is_none_or(|shares| split_shares(*shares, new_shares, old_shares).is_some());
+        fits(self.borrow_pool.get(stock))
+            && self.accounts.values().all(|account| {
+                [&account.short_positions, &account.committed, &account.located, &account.covering]
+                    .iter()
+                    .all(|shares| fits(shares.get(stock)))
+            })
+    }
+
+    // Scale positions for a split, settling fractional shares in cash at the post-split price.
+    // The caller checks `split_fits` first.
+    pub fn apply_split(&mut self, stock: &str, new_shares: u32, old_shares: u32, price: f64) {
+        let split = |shares: u32| split_shares(shares, new_shares, old_shares).expect("checked by split_fits");
+        let (new_shares, old_shares) = (new_shares as u64, old_shares as u64);
+        let scale = |shares: u64| shares * new_shares / old_shares;
+        let fraction = |shares: u64| (shares * new_shares % old_shares) as f64 / old_shares as f64;
+
+        for account in self.accounts.values_mut() {
+            if let Some(position) = account.positions.get_mut(stock) {
+                let held = (*position).max(0) as u64;
+                account.cash += fraction(held) * price;
+                *position = scale(held) as i64;
+                if *position == 0 {
+                    account.positions.remove(stock);
+                }
+            }
+            if let Some(short) = account.short_positions.get_mut(stock) {
+                account.cash -= fraction(*short as u64) * price;
+                *short = split(*short);
+                if *short == 0 {
+                    account.short_positions.remove(stock);
+                }
+            }
+            for reserved in [&mut account.committed, &mut account.located, &mut account.covering] {
+                if let Some(shares) = reserved.get_mut(stock) {
+                    *shares = split(*shares);
+                }
+            }
+            if let Some(unsettled) = account.unsettled_positions.get_mut(stock) {
+                *unsettled = *unsettled * new_shares as i64 / old_shares as i64;
+            }
+        }
+        self.clearing.apply_split(stock, new_shares as u32, old_shares as u32);
+        if let Some(shares) = self.borrow_pool.get_mut(stock) {
+            *shares = split(*shares);
+        }
+    }
+
+    pub fn rename_symbol(&mut self, old: &str, new: &str) {
+        for account in self.accounts.values_mut() {
+            for positions in [&mut account.positions, &mut account.unsettled_positions] {
+                if let Some(position) = positions.remove(old) {
+                    positions.insert(new.to_string(), position);
+                }
+            }
+            for shares in [
+                &mut account.short_positions,
+                &mut account.committed,
+                &mut account.located,
+                &mut account.covering,
+            ] {
+                rename(shares, old, new);
+            }
+        }
+        rename(&mut self.borrow_pool, old, new);
+        self.clearing.rename_symbol(old, new);
+    }
+
+    // Execution reports queued since the last call
+    pub fn take_reports(&mut self) -> Vec<ExecutionReport> {
+        std::mem::take(&mut self.reports)
+    }
+
+    // Accounts ordered by client id
+    pub fn iter(&self) -> impl Iterator<Item = (&u32, &Account)> {
+        self.accounts.iter()
+    }
+
+    fn report(&mut self, stock: &str, order: &RestingOrder, status: &str, price: f64, reason: Option<String>) {
+        self.reports.push(execution_report(stock, order, status, price, reason));
+    }
+}
+
+fn execution_report(stock: &str, order: &RestingOrder, status: &str, price: f64, reason: Option<String>) -> ExecutionReport {
+    ExecutionReport {
+        order_id: order.order_id,
+        client_id: order.client_id,
+        stock: stock.to_string(),
+        action: order.action.clone(),
+        status: status.to_string(),
+        quantity: order.quantity,
+        price,
+        reason,
+        broker_id: order.broker_id,
+        fee: 0.0,
+        liquidity: None,
+        venue: String::new(), // Filled in by the execution publisher
+        session: order.session.clone(),
+        exchange_id: order.exchange_id.clone(),
+    }
+}
+
+fn get(shares: &BTreeMap<String, u32>, stock: &str) -> u32 {
+    shares.get(stock).copied().unwrap_or(0)
+}
+
+fn add(shares: &mut BTreeMap<String, u32>, stock: &str, quantity: u32) {
+    *shares.entry(stock.to_string()).or_insert(0) += quantity;
+}
+
+fn take(shares: &mut BTreeMap<String, u32>, stock: &str, quantity: u32) {
+    if let Some(held) = shares.get_mut(stock) {
+        *held = held.saturating_sub(quantity);
+        if *held == 0 {
+            shares.remove(stock);
+        }
+    }
+}
+
+fn add_signed(shares: &mut BTreeMap<String, i64>, stock: &str, quantity: i64) {
+    let held = shares.entry(stock.to_string()).or_insert(0);
+    *held += quantity;
+    if *held == 0 {
+        shares.remove(stock);
+    }
+}
+
+fn rename(shares: &mut BTreeMap<String, u32>, old: &str, new: &str) {
+    if let Some(quantity) = shares.remove(old) {
+        shares.insert(new.to_string(), quantity);
+    }
+}
@@ -0,0 +1,348 @@
+use crate::algos::AlgoParams;
+use crate::market_data::{ExecutionReport, MarketNews, Quote};
+use crate::order_groups::LinkedPrices;
+use crate::strategy::{round_cents, NoiseParams, OwnOrders, Population, Signal, Strategy, StrategySpec};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::time::{Duration, Instant};
+
+// Default location of the agent population mix
+pub const AGENTS_PATH: &str = "config/agents.json";
+
+// Trades towards a private valuation of one symbol, formed from the first price it sees
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FundamentalParams {
+    pub valuation_noise: f64, // Largest error of the private valuation, as a fraction of the price
+    pub threshold: f64,       // Mispricing, as a fraction of the valuation, before trading
+    pub quantity: u32,
+    pub stop_loss: f64, // Loss, as a fraction of the entry, at which a bracketed position is given up; 0 for no brackets
+}
+
+impl Default for FundamentalParams {
+    fn default() -> Self {
+        Self { valuation_noise: 0.05, threshold: 0.02, quantity: 20, stop_loss: 0.05 }
+    }
+}
+
+// Jumps on a symbol's recent trend
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MomentumParams {
+    pub lookback: usize,
+    pub threshold: f64,
+    pub quantity: u32,
+}
+
+impl Default for MomentumParams {
+    fn default() -> Self {
+        Self { lookback: 5, threshold: 0.01, quantity: 10 }
+    }
+}
+
+// Works a large parent order as a series of small market orders
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct InstitutionalParams {
+    pub parent_quantity: u32,
+    pub slice_quantity: u32,
+    pub slice_interval_secs: u64,
+    pub algo: String, // Broker algo given the whole parent ("TWAP", "VWAP", "Iceberg" or "POV"); empty to slice it here
+}
+
+impl Default for InstitutionalParams {
+    fn default() -> Self {
+        Self { parent_quantity: 400, slice_quantity: 20, slice_interval_secs: 3, algo: "TWAP".to_string() }
+    }
+}
+
+// Builds up holdings slowly and dumps all of them on bad enough news
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PanicParams {
+    pub trigger: f64, // Negative news impact, as a fraction, that sets off the selling
+    pub accumulate_quantity: u32,
+    pub accumulate_delay_secs: u64,
+}
+
+impl Default for PanicParams {
+    fn default() -> Self {
+        Self { trigger: 0.1, accumulate_quantity: 10, accumulate_delay_secs: 15 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "archetype")]
+pub enum Archetype {
+    Noise(NoiseParams),
+    Fundamental(FundamentalParams),
+    MomentumChaser(MomentumParams),
+    Institutional(InstitutionalParams),
+    PanicSeller(PanicParams),
+}
+
+// How many agents of one archetype join the market
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ArchetypeCount {
+    pub count: u32,
+    #[serde(flatten)]
+    pub archetype: Archetype,
+}
+
+// Population of synthetic clients by archetype
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AgentMix {
+    pub agents: Vec<ArchetypeCount>,
+}
+
+impl Default for AgentMix {
+    fn default() -> Self {
+        let mix = |count, archetype| ArchetypeCount { count, archetype };
+        Self {
+            agents: vec![
+                mix(5, Archetype::Noise(NoiseParams::default())),
+                mix(3, Archetype::Fundamental(FundamentalParams::default())),
+                mix(2, Archetype::MomentumChaser(MomentumParams::default())),
+                mix(1, Archetype::Institutional(InstitutionalParams::default())),
+                mix(2, Archetype::PanicSeller(PanicParams::default())),
+            ],
+        }
+    }
+}
+
+impl AgentMix {
+    // Load the mix from disk, falling back to the default
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                println!("[Agents] Invalid agent mix {}: {}, using defaults", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    // One client per agent, numbered from `first_client_id`. Agents that follow a single symbol
+    // get a random one of `symbols`.
+    pub fn build(&self, first_client_id: u32, symbols: &[String]) -> Population {
+        let mut rng = rand::thread_rng();
+        let mut population: Population = Vec::new();
+        let mut client_id = first_client_id;
+
+        for group in &self.agents {
+            for _ in 0..group.count {
+                let stock = symbols[rng.gen_range(0..symbols.len())].clone();
+                let strategy: Box<dyn Strategy> = match group.archetype.clone() {
+                    Archetype::Noise(params) => StrategySpec::Random { client_id, params }.build().1,
+                    Archetype::Fundamental(params) => Box::new(FundamentalTrader::new(stock, params)),
+                    Archetype::MomentumChaser(params) => {
+                        StrategySpec::Momentum {
+                            client_id,
+                            stock,
+                            lookback: params.lookback,
+                            threshold: params.threshold,
+                            quantity: params.quantity,
+                        }
+                        .build()
+                        .1
+                    }
+                    Archetype::Institutional(params) => Box::new(InstitutionalSlicer::new(stock, params)),
+                    Archetype::PanicSeller(params) => Box::new(PanicSeller::new(stock, params)),
+                };
+                population.push((client_id, strategy));
+                client_id += 1;
+            }
+        }
+        population
+    }
+}
+
+pub struct FundamentalTrader {
+    own: OwnOrders,
+    stock: String,
+    params: FundamentalParams,
+    valuation: Option<f64>,
+}
+
+impl FundamentalTrader {
+    fn new(stock: String, params: FundamentalParams) -> Self {
+        Self { own: OwnOrders::default(), stock, params, valuation: None }
+    }
+}
+
+impl Strategy for FundamentalTrader {
+    fn name(&self) -> &str {
+        "Fundamental"
+    }
+
+    fn on_execution(&mut self, report: &ExecutionReport) -> Vec<Signal> {
+        self.own.on_execution(report);
+        Vec::new()
+    }
+
+    fn on_quote(&mut self, stock: &str, quote: &Quote) -> Vec<Signal> {
+        if stock != self.stock {
+            return Vec::new();
+        }
+        let noise = self.params.valuation_noise.abs();
+        let valuation = *self.valuation.get_or_insert_with(|| {
+            let error = if noise > 0.0 { rand::thread_rng().gen_range(-noise..=noise) } else { 0.0 };
+            quote.last * (1.0 + error)
+        });
+        if !self.own.is_idle() {
+            return Vec::new();
+        }
+
+        // Cheap: own it; rich: short it; fairly priced: hold what it has
+        let quantity = self.params.quantity as i64;
+        let target = if quote.last < valuation * (1.0 - self.params.threshold) {
+            quantity
+        } else if quote.last > valuation * (1.0 + self.params.threshold) {
+            -quantity
+        } else {
+            self.own.position(stock)
+        };
+
+        // From flat, enter with a bracket: take profit at the valuation, stop out if the price runs the other way
+        if self.params.stop_loss > 0.0 && target != 0 && self.own.position(stock) == 0 {
+            let (action, stop) = if target > 0 {
+                ("Buy", quote.last * (1.0 - self.params.stop_loss))
+            } else {
+                ("SellShort", quote.last * (1.0 + self.params.stop_loss))
+            };
+            let links = LinkedPrices {
+                take_profit: round_cents(valuation),
+                stop_loss: round_cents(stop),
+                ..LinkedPrices::default()
+            };
+            let quantity = self.params.quantity;
+            return vec![self.own.submit_linked(stock, action, quantity, "Bracket", quote.last, links)];
+        }
+        self.own.trade_to(stock, target, "Limit", quote.last)
+    }
+}
+
+pub struct InstitutionalSlicer {
+    own: OwnOrders,
+    stock: String,
+    params: InstitutionalParams,
+    buying: bool,
+    left: u32, // Of the current parent order
+    next_slice: Instant,
+}
+
+impl InstitutionalSlicer {
+    fn new(stock: String, params: InstitutionalParams) -> Self {
+        let left = params.parent_quantity;
+        Self { own: OwnOrders::default(), stock, params, buying: true, left, next_slice: Instant::now() }
+    }
+}
+
+impl Strategy for InstitutionalSlicer {
+    fn name(&self) -> &str {
+        "Institutional"
+    }
+
+    fn on_execution(&mut self, report: &ExecutionReport) -> Vec<Signal> {
+        self.own.on_execution(report);
+        Vec::new()
+    }
+
+    fn on_timer(&mut self, quotes: &HashMap<String, Quote>) -> Vec<Signal> {
+        if Instant::now() < self.next_slice || !self.own.is_idle() {
+            return Vec::new();
+        }
+        self.next_slice = Instant::now() + Duration::from_secs(self.params.slice_interval_secs);
+        let Some(quote) = quotes.get(&self.stock) else { return Vec::new() };
+
+        // Once bought, the position is worked back out the same way
+        if self.left == 0 {
+            let held = self.own.position(&self.stock).max(0) as u32;
+            self.buying = held == 0;
+            self.left = if self.buying { self.params.parent_quantity } else { held };
+            println!(
+                "[Agent] Institutional parent order: {} {} x{}",
+                if self.buying { "Buy" } else { "Sell" },
+                self.stock,
+                self.left
+            );
+        }
+        let slice_quantity = self.params.slice_quantity.max(1);
+        let action = if self.buying { "Buy" } else { "Sell" };
+        let stock = self.stock.clone();
+        if !self.params.algo.is_empty() {
+            // The broker works the whole parent over the time the slices would have taken
+            let slices = self.left.div_ceil(slice_quantity);
+            let params = AlgoParams {
+                duration_secs: self.params.slice_interval_secs * slices as u64,
+                slices,
+                display_quantity: slice_quantity,
+                ..AlgoParams::default()
+            };
+            let quantity = std::mem::take(&mut self.left);
+            return vec![self.own.submit_algo(&stock, action, quantity, &self.params.algo, quote.last, params)];
+        }
+        let slice = self.left.min(slice_quantity);
+        self.left -= slice;
+        vec![self.own.submit(&stock, action, slice, "Market", quote.last)]
+    }
+}
+
+pub struct PanicSeller {
+    own: OwnOrders,
+    stock: String,
+    params: PanicParams,
+    next_purchase: Instant,
+    panic: bool, // Set by bad news, cleared once the holdings are gone
+}
+
+impl PanicSeller {
+    fn new(stock: String, params: PanicParams) -> Self {
+        Self { own: OwnOrders::default(), stock, params, next_purchase: Instant::now(), panic: false }
+    }
+}
+
+impl Strategy for PanicSeller {
+    fn name(&self) -> &str {
+        "PanicSeller"
+    }
+
+    fn on_execution(&mut self, report: &ExecutionReport) -> Vec<Signal> {
+        self.own.on_execution(report);
+        Vec::new()
+    }
+
+    fn on_news(&mut self, news: &MarketNews) -> Vec<Signal> {
+        if news.impact > -self.params.trigger {
+            return Vec::new();
+        }
+        println!("[Agent] Panic selling {} on {}", self.stock, news.event_name);
+        self.panic = true;
+        // Working purchases come out first; the holdings go at the next timer
+        self.own.cancel_all()
+    }
+
+    fn on_timer(&mut self, quotes: &HashMap<String, Quote>) -> Vec<Signal> {
+        let Some(quote) = quotes.get(&self.stock) else { return Vec::new() };
+        let stock = self.stock.clone();
+
+        if self.panic {
+            let held = self.own.position(&stock);
+            if held <= 0 {
+                self.panic = false;
+            } else if self.own.is_idle() {
+                return self.own.trade_to(&stock, 0, "Market", quote.last);
+            }
+            return Vec::new();
+        }
+
+        if Instant::now() < self.next_purchase || !self.own.is_idle() {
+            return Vec::new();
+        }
+        self.next_purchase = Instant::now() + Duration::from_secs(self.params.accumulate_delay_secs);
+        vec![self.own.submit(&stock, "Buy", self.params.accumulate_quantity, "Limit", quote.last)]
+    }
+}
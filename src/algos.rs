@@ -0,0 +1,314 @@
+use crate::brokers::Order;
+use crate::market_data::ExecutionReport;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Expected share of the volume in each equal part of a VWAP window: heavy at the start and the end
+const DEFAULT_VOLUME_PROFILE: [f64; 6] = [0.25, 0.15, 0.1, 0.1, 0.15, 0.25];
+
+// Order types a broker works itself, slicing the parent order into child orders
+pub fn is_algo(order_type: &str) -> bool {
+    matches!(order_type, "TWAP" | "VWAP" | "Iceberg" | "POV")
+}
+
+// How an execution algorithm works its parent order
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AlgoParams {
+    pub duration_secs: u64,       // Window of a TWAP or VWAP; a POV order stops after it unless 0
+    pub slices: u32,              // TWAP: equal child orders spread over the window
+    pub volume_profile: Vec<f64>, // VWAP: share of the volume expected in each equal part of the window
+    pub display_quantity: u32,    // Iceberg: shown at a time; the rest is held back until it fills
+    pub participation: f64,       // POV: fraction of the market's volume to trade
+    pub child_order_type: String, // "Market" or "Limit" at the parent's price; iceberg children are always limits
+}
+
+impl Default for AlgoParams {
+    fn default() -> Self {
+        Self {
+            duration_secs: 60,
+            slices: 6,
+            volume_profile: Vec::new(),
+            display_quantity: 100,
+            participation: 0.1,
+            child_order_type: "Market".to_string(),
+        }
+    }
+}
+
+// A parent order a broker is working, and what its child orders have done so far
+pub struct ParentOrder {
+    pub order: Order,
+    params: AlgoParams,
+    start: Option<u64>,         // Milliseconds since the Unix epoch, from the first timer tick after it arrived
+    pub filled: u32,
+    notional: f64,              // Filled quantity times price, for the average price
+    working: HashMap<u32, u32>, // Child order id to what is still open of it
+    market_volume: u64,         // Traded in the symbol since the start, for POV
+    pub finishing: Option<(String, String)>, // Final status and reason once the parent is being wound up
+}
+
+impl ParentOrder {
+    pub fn new(mut order: Order) -> Self {
+        let params = order.algo.take().unwrap_or_default();
+        Self {
+            order,
+            params,
+            start: None,
+            filled: 0,
+            notional: 0.0,
+            working: HashMap::new(),
+            market_volume: 0,
+            finishing: None,
+        }
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.order.quantity.saturating_sub(self.filled)
+    }
+
+    pub fn average_price(&self) -> f64 {
+        if self.filled == 0 {
+            0.0
+        } else {
+            self.notional / self.filled as f64
+        }
+    }
+
+    // The window starts with the first tick the parent sees
+    pub fn tick(&mut self, now: u64) {
+        self.start.get_or_insert(now);
+    }
+
+    pub fn working_children(&self) -> Vec<u32> {
+        self.working.keys().copied().collect()
+    }
+
+    // Nothing more will trade: fully filled, or wound up with no child left working
+    pub fn is_done(&self) -> bool {
+        self.remaining() == 0 || (self.finishing.is_some() && self.working.is_empty())
+    }
+
+    // The window has passed; icebergs have none
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.order.order_type != "Iceberg"
+            && self.params.duration_secs > 0
+            && now >= self.start.unwrap_or(now) + self.params.duration_secs * 1000
+    }
+
+    // Market children of an expired TWAP or VWAP finish the order; anything else is wound up
+    pub fn winds_up_at_expiry(&self) -> bool {
+        self.order.order_type == "POV" || self.child_order_type() != "Market"
+    }
+
+    // Quantity to send as a new child now: what the schedule has released less what is filled or working
+    pub fn due(&self, now: u64) -> u32 {
+        if self.finishing.is_some() {
+            return 0;
+        }
+        let working: u32 = self.working.values().sum();
+        let released = match self.order.order_type.as_str() {
+            "TWAP" => self.scheduled(now, &vec![1.0; self.params.slices.max(1) as usize]),
+            "VWAP" if self.params.volume_profile.iter().any(|share| *share > 0.0) => {
+                self.scheduled(now, &self.params.volume_profile)
+            }
+            "VWAP" => self.scheduled(now, &DEFAULT_VOLUME_PROFILE),
+            "POV" => (self.market_volume as f64 * self.params.participation.clamp(0.0, 1.0)) as u32,
+            // The next clip is only shown once the last one has filled
+            _ if working > 0 => return 0,
+            _ => self.filled + self.params.display_quantity.max(1),
+        };
+        released.min(self.order.quantity).saturating_sub(self.filled + working)
+    }
+
+    // Quantity released by `now` when each part of the window gets its share of the order at its start
+    fn scheduled(&self, now: u64, profile: &[f64]) -> u32 {
+        let window = (self.params.duration_secs * 1000).max(1);
+        let parts = profile.len() as u64;
+        let elapsed = now.saturating_sub(self.start.unwrap_or(now));
+        let part = ((elapsed * parts / window) as usize).min(profile.len() - 1);
+        let total: f64 = profile.iter().map(|share| share.max(0.0)).sum();
+        let share: f64 = profile[..=part].iter().map(|share| share.max(0.0)).sum::<f64>() / total;
+        (self.order.quantity as f64 * share).round() as u32
+    }
+
+    fn child_order_type(&self) -> &str {
+        if self.order.order_type == "Iceberg" || self.params.child_order_type == "Limit" {
+            "Limit"
+        } else {
+            "Market"
+        }
+    }
+
+    pub fn child(&mut self, order_id: u32, quantity: u32) -> Order {
+        self.working.insert(order_id, quantity);
+        Order {
+            order_id,
+            quantity,
+            order_type: self.child_order_type().to_string(),
+            venue: String::new(),
+            algo: None,
+            links: None,
+            ..self.order.clone()
+        }
+    }
+
+    // Track a child's report; a fill comes back as a fill of the parent
+    pub fn on_child_report(&mut self, report: &ExecutionReport) -> Option<ExecutionReport> {
+        let open = self.working.get_mut(&report.order_id)?;
+        let fill = match report.status.as_str() {
+            // Sent quantity is already counted as working
+            "Accepted" => return None,
+            "Filled" => {
+                self.filled += report.quantity;
+                self.notional += report.quantity as f64 * report.price;
+                Some(ExecutionReport { order_id: self.order.order_id, ..report.clone() })
+            }
+            _ => None,
+        };
+        *open = open.saturating_sub(report.quantity);
+        if *open == 0 {
+            self.working.remove(&report.order_id);
+        }
+        fill
+    }
+
+    pub fn on_trade(&mut self, quantity: u32) {
+        self.market_volume += quantity as u64;
+    }
+
+    pub fn progress(&self) -> String {
+        format!(
+            "{} order {} {} {}: {}/{} filled ({:.1}%), avg price {:.2}",
+            self.order.order_type,
+            self.order.order_id,
+            self.order.action,
+            self.order.stock,
+            self.filled,
+            self.order.quantity,
+            self.filled as f64 * 100.0 / self.order.quantity.max(1) as f64,
+            self.average_price()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parent(order_type: &str, quantity: u32, params: AlgoParams) -> ParentOrder {
+        let order = Order {
+            order_id: 1,
+            client_id: 7,
+            broker_id: 1,
+            session: String::new(),
+            stock: "AAPL".to_string(),
+            action: "Buy".to_string(),
+            quantity,
+            price: 10.0,
+            order_type: order_type.to_string(),
+            time_in_force: "Day".to_string(),
+            venue: String::new(),
+            algo: Some(params),
+            links: None,
+        };
+        let mut parent = ParentOrder::new(order);
+        parent.tick(0);
+        parent
+    }
+
+    fn fill(order_id: u32, quantity: u32, price: f64) -> ExecutionReport {
+        ExecutionReport {
+            order_id,
+            client_id: 7,
+            stock: "AAPL".to_string(),
+            action: "Buy".to_string(),
+            status: "Filled".to_string(),
+            quantity,
+            price,
+            reason: None,
+            broker_id: 1,
+            fee: 0.0,
+            liquidity: Some("Taker".to_string()),
+            venue: String::new(),
+            session: String::new(),
+            exchange_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn twap_releases_equal_slices_over_the_window() {
+        let params = AlgoParams { duration_secs: 60, slices: 6, ..AlgoParams::default() };
+        let mut twap = parent("TWAP", 600, params);
+
+        assert_eq!(twap.due(0), 100);
+        twap.child(101, 100);
+        assert_eq!(twap.due(9_999), 0);
+        assert_eq!(twap.due(10_000), 100);
+
+        // Child fills come back as fills of the parent
+        let report = twap.on_child_report(&fill(101, 100, 10.0)).expect("parent fill");
+        assert_eq!((report.order_id, report.quantity), (1, 100));
+        assert_eq!(twap.due(10_000), 100);
+        assert_eq!(twap.due(120_000), 500);
+    }
+
+    #[test]
+    fn vwap_follows_the_volume_profile() {
+        let params = AlgoParams { duration_secs: 60, ..AlgoParams::default() };
+        let vwap = parent("VWAP", 1000, params);
+
+        assert_eq!(vwap.due(0), 250);
+        assert_eq!(vwap.due(10_000), 400);
+        assert_eq!(vwap.due(59_999), 1000);
+    }
+
+    #[test]
+    fn iceberg_shows_the_next_clip_once_the_last_one_fills() {
+        let params = AlgoParams { display_quantity: 100, ..AlgoParams::default() };
+        let mut iceberg = parent("Iceberg", 250, params);
+
+        assert_eq!(iceberg.due(0), 100);
+        iceberg.child(101, 100);
+        iceberg.on_child_report(&fill(101, 40, 10.0));
+        assert_eq!(iceberg.due(0), 0);
+        iceberg.on_child_report(&fill(101, 60, 10.0));
+        assert_eq!(iceberg.due(0), 100);
+        iceberg.child(102, 100);
+        iceberg.on_child_report(&fill(102, 100, 10.0));
+        assert_eq!(iceberg.due(0), 50);
+        assert!(!iceberg.is_expired(u64::MAX));
+    }
+
+    #[test]
+    fn pov_trades_its_share_of_the_market_volume() {
+        let params = AlgoParams { participation: 0.1, ..AlgoParams::default() };
+        let mut pov = parent("POV", 1000, params);
+
+        assert_eq!(pov.due(0), 0);
+        pov.on_trade(1000);
+        assert_eq!(pov.due(0), 100);
+        pov.child(101, 100);
+        pov.on_trade(500);
+        assert_eq!(pov.due(0), 50);
+        assert!(pov.winds_up_at_expiry());
+    }
+
+    #[test]
+    fn fills_add_up_to_the_average_price_and_finish_the_parent() {
+        let params = AlgoParams { duration_secs: 60, slices: 2, ..AlgoParams::default() };
+        let mut twap = parent("TWAP", 200, params);
+        twap.child(101, 100);
+        twap.child(102, 100);
+
+        twap.on_child_report(&fill(101, 100, 10.0));
+        assert!(!twap.is_done());
+        twap.on_child_report(&fill(102, 100, 11.0));
+
+        assert!(twap.is_done());
+        assert_eq!(twap.average_price(), 10.5);
+        assert!(twap.working_children().is_empty());
+        assert!(twap.is_expired(60_000));
+        assert!(!twap.winds_up_at_expiry());
+    }
+}
@@ -0,0 +1,273 @@
+use crate::market_data::{from_ticks, is_buy, to_ticks, AuctionUpdate};
+use crate::order_book::{IncomingOrder, RestingOrder};
+use std::cmp::Reverse;
+
+// Orders collected for one symbol during a call auction, in arrival order
+#[derive(Debug, Default)]
+pub struct CallAuction {
+    orders: Vec<IncomingOrder>,
+    published: Option<(Option<u64>, u64, i64)>, // Last indicative price, volume and imbalance sent
+}
+
+// Buyer and seller crossed at the uncrossing price, each for the quantity traded
+#[derive(Debug, Clone)]
+pub struct AuctionFill {
+    pub buy: RestingOrder,
+    pub sell: RestingOrder,
+}
+
+// Result of uncrossing an auction: every fill happens at the same price
+#[derive(Debug, Clone)]
+pub struct Uncross {
+    pub price: f64,
+    pub volume: u64,
+    pub imbalance: i64,
+    pub fills: Vec<AuctionFill>,
+}
+
+impl CallAuction {
+    pub fn add(&mut self, order: IncomingOrder) {
+        if order.quantity > 0 {
+            self.orders.push(order);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    // The indicative price, matched volume and imbalance if it changed since the last call
+    pub fn indicative_update(&mut self, stock: &str, auction: &str, reference: f64) -> Option<AuctionUpdate> {
+        let (ticks, volume, imbalance) = match equilibrium(&self.orders, reference) {
+            Some((ticks, volume, imbalance)) => (Some(ticks), volume, imbalance),
+            None => (None, 0, total_imbalance(&self.orders)),
+        };
+        if self.published == Some((ticks, volume, imbalance)) {
+            return None;
+        }
+        self.published = Some((ticks, volume, imbalance));
+
+        Some(AuctionUpdate {
+            stock: stock.to_string(),
+            auction: auction.to_string(),
+            indicative_price: ticks.map(from_ticks),
+            matched_volume: volume,
+            imbalance,
+            uncrossed: false,
+        })
+    }
+
+    // Cross buyers and sellers at the equilibrium price, highest-priority orders first.
+    // Returns the uncross (None if nothing crossed) and whatever was left unexecuted, in arrival order.
+    pub fn uncross(&mut self, reference: f64) -> (Option<Uncross>, Vec<IncomingOrder>) {
+        let orders = std::mem::take(&mut self.orders);
+        self.published = None;
+
+        let Some((ticks, volume, imbalance)) = equilibrium(&orders, reference) else {
+            return (None, orders);
+        };
+
+        // Market orders first, then the most aggressive limits; ties keep arrival order
+        let (mut buys, rest): (Vec<_>, Vec<_>) = orders
+            .into_iter()
+            .enumerate()
+            .partition(|(_, order)| is_buy(&order.action) && crosses(order, ticks));
+        let (mut sells, mut remaining): (Vec<_>, Vec<_>) = rest
+            .into_iter()
+            .partition(|(_, order)| !is_buy(&order.action) && crosses(order, ticks));
+        buys.sort_by_key(|(_, order)| Reverse(limit_ticks(order).unwrap_or(u64::MAX)));
+        sells.sort_by_key(|(_, order)| limit_ticks(order).unwrap_or(0));
+
+        let mut fills = Vec::new();
+        let mut left = volume;
+        let (mut b, mut s) = (0, 0);
+        while left > 0 {
+            let (buy, sell) = (&mut buys[b].1, &mut sells[s].1);
+            let traded = (buy.quantity.min(sell.quantity) as u64).min(left) as u32;
+            buy.quantity -= traded;
+            sell.quantity -= traded;
+            left -= traded as u64;
+            fills.push(AuctionFill { buy: buy.resting(traded), sell: sell.resting(traded) });
+            if buy.quantity == 0 {
+                b += 1;
+            }
+            if sell.quantity == 0 {
+                s += 1;
+            }
+        }
+
+        remaining.extend(buys.into_iter().chain(sells).filter(|(_, order)| order.quantity > 0));
+        remaining.sort_by_key(|(i, _)| *i);
+
+        let uncross = Uncross { price: from_ticks(ticks), volume, imbalance, fills };
+        (Some(uncross), remaining.into_iter().map(|(_, order)| order).collect())
+    }
+
+    // Withdraw a collected order at its client's request
+    pub fn cancel(&mut self, session: &str, order_id: u32, client_id: u32) -> Option<IncomingOrder> {
+        let index = self
+            .orders
+            .iter()
+            .position(|order| order.session == session && order.order_id == order_id && order.client_id == client_id)?;
+        Some(self.orders.remove(index))
+    }
+
+    // Remove every collected order, e.g. when the market closes mid-auction
+    pub fn take_orders(&mut self) -> Vec<IncomingOrder> {
+        self.published = None;
+        std::mem::take(&mut self.orders)
+    }
+}
+
+// Price (in ticks) that maximises executed volume, then minimises the imbalance, then stays
+// closest to the reference price. None if no buy and sell interest cross at any price.
+fn equilibrium(orders: &[IncomingOrder], reference: f64) -> Option<(u64, u64, i64)> {
+    let reference = to_ticks(reference);
+    let mut candidates: Vec<u64> = orders.iter().filter_map(limit_ticks).collect();
+    candidates.push(reference);
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    candidates
+        .into_iter()
+        .map(|ticks| {
+            let demand = executable(orders, true, ticks);
+            let supply = executable(orders, false, ticks);
+            (ticks, demand.min(supply), demand as i64 - supply as i64)
+        })
+        .filter(|(_, volume, _)| *volume > 0)
+        .min_by_key(|(ticks, volume, imbalance)| (Reverse(*volume), imbalance.unsigned_abs(), ticks.abs_diff(reference)))
+}
+
+// Quantity on one side willing to trade at the given price
+fn executable(orders: &[IncomingOrder], buy: bool, ticks: u64) -> u64 {
+    orders
+        .iter()
+        .filter(|order| is_buy(&order.action) == buy && crosses(order, ticks))
+        .map(|order| order.quantity as u64)
+        .sum()
+}
+
+fn total_imbalance(orders: &[IncomingOrder]) -> i64 {
+    orders
+        .iter()
+        .map(|order| if is_buy(&order.action) { order.quantity as i64 } else { -(order.quantity as i64) })
+        .sum()
+}
+
+fn crosses(order: &IncomingOrder, ticks: u64) -> bool {
+    match limit_ticks(order) {
+        None => true,
+        Some(limit) if is_buy(&order.action) => limit >= ticks,
+        Some(limit) => limit <= ticks,
+    }
+}
+
+fn limit_ticks(order: &IncomingOrder) -> Option<u64> {
+    if order.order_type == "Limit" {
+        Some(to_ticks(order.price))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(order_id: u32, action: &str, order_type: &str, price: f64, quantity: u32) -> IncomingOrder {
+        IncomingOrder {
+            order_id,
+            client_id: order_id,
+            broker_id: 1,
+            session: String::new(),
+            exchange_id: String::new(),
+            stock: "AAPL".to_string(),
+            action: action.to_string(),
+            quantity,
+            order_type: order_type.to_string(),
+            price,
+            time_in_force: "Day".to_string(),
+        }
+    }
+
+    fn auction(orders: Vec<IncomingOrder>) -> CallAuction {
+        let mut auction = CallAuction::default();
+        for order in orders {
+            auction.add(order);
+        }
+        auction
+    }
+
+    // Buyer, seller and shares of each fill
+    fn crossed(uncross: &Uncross) -> Vec<(u32, u32, u32)> {
+        uncross.fills.iter().map(|fill| (fill.buy.order_id, fill.sell.order_id, fill.buy.quantity)).collect()
+    }
+
+    #[test]
+    fn uncrosses_at_the_price_that_trades_most_closest_to_the_reference() {
+        let mut auction = auction(vec![
+            order(1, "Buy", "Limit", 10.10, 100),
+            order(2, "Buy", "Limit", 10.00, 100),
+            order(3, "Sell", "Limit", 9.90, 150),
+            order(4, "Sell", "Limit", 10.05, 100),
+        ]);
+
+        // 150 shares trade at both 9.90 and 10.00 with the same imbalance; 10.00 is the reference
+        let (uncross, leftovers) = auction.uncross(10.00);
+        let uncross = uncross.expect("orders cross");
+
+        assert_eq!((uncross.price, uncross.volume, uncross.imbalance), (10.00, 150, 50));
+        assert_eq!(crossed(&uncross), vec![(1, 3, 100), (2, 3, 50)]);
+
+        // What did not trade comes back in arrival order
+        let leftovers: Vec<(u32, u32)> = leftovers.iter().map(|order| (order.order_id, order.quantity)).collect();
+        assert_eq!(leftovers, vec![(2, 50), (4, 100)]);
+        assert_eq!(auction.len(), 0);
+    }
+
+    #[test]
+    fn market_orders_trade_ahead_of_limits() {
+        let mut auction = auction(vec![
+            order(1, "Buy", "Limit", 10.20, 100),
+            order(2, "Buy", "Market", 0.0, 100),
+            order(3, "Sell", "Limit", 10.00, 100),
+        ]);
+
+        let (uncross, leftovers) = auction.uncross(10.00);
+
+        assert_eq!(crossed(&uncross.expect("orders cross")), vec![(2, 3, 100)]);
+        assert_eq!(leftovers.iter().map(|order| order.order_id).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn nothing_trades_when_the_sides_do_not_cross() {
+        let mut auction = auction(vec![order(1, "Buy", "Limit", 9.90, 100), order(2, "Sell", "Limit", 10.00, 100)]);
+
+        let (uncross, leftovers) = auction.uncross(9.95);
+
+        assert!(uncross.is_none());
+        assert_eq!(leftovers.len(), 2);
+    }
+
+    #[test]
+    fn indicative_update_is_only_sent_when_it_changes() {
+        let mut auction = auction(vec![order(1, "Buy", "Limit", 10.00, 100), order(2, "Sell", "Limit", 10.00, 60)]);
+
+        let update = auction.indicative_update("AAPL", "Opening", 10.00).expect("first update");
+        assert_eq!((update.indicative_price, update.matched_volume, update.imbalance), (Some(10.00), 60, 40));
+        assert!(auction.indicative_update("AAPL", "Opening", 10.00).is_none());
+
+        auction.add(order(3, "Sell", "Limit", 9.95, 40));
+        let update = auction.indicative_update("AAPL", "Opening", 10.00).expect("changed");
+        assert_eq!((update.matched_volume, update.imbalance), (100, 0));
+
+        assert!(auction.cancel("", 3, 3).is_some());
+        let update = auction.indicative_update("AAPL", "Opening", 10.00).expect("changed by the cancel");
+        assert_eq!((update.matched_volume, update.imbalance), (60, 40));
+    }
+}
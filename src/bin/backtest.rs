@@ -0,0 +1,284 @@
+use rts::{brokers, fees, fill_model, history, margin, market_data, performance, portfolio, routing, strategy};
+
+use brokers::{Broker, Order};
+use fees::{FeeSchedules, FEES_PATH};
+use fill_model::{FillModel, SimulatedExchange};
+use history::{load_csv, load_recording, MarketRecord, RECORDING_PATH};
+use margin::{new_order, MarginConfig, MARGIN_PATH};
+use market_data::{ExecutionReport, Quote};
+use performance::{ClientPerformance, Performance, TradeLogEntry};
+use portfolio::Portfolio;
+use routing::{Router, RoutingConfig, ROUTING_PATH};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use strategy::{dispatch, load_population, MarketEvent, Population, Signal, STRATEGIES_PATH};
+
+// Default location of the backtest settings
+const BACKTEST_PATH: &str = "config/backtest.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct BacktestConfig {
+    data: String, // A recording made by the trader, or a CSV file ending in ".csv"
+    fill_model: FillModel,
+    sample_secs: u64,      // Data time between equity samples
+    periods_per_year: f64, // Samples in a trading year, to annualize the Sharpe ratio
+    report_path: String,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            data: RECORDING_PATH.to_string(),
+            fill_model: FillModel::default(),
+            sample_secs: 1,
+            periods_per_year: 252.0 * 6.5 * 3600.0,
+            report_path: "reports/backtest.json".to_string(),
+        }
+    }
+}
+
+impl BacktestConfig {
+    // Load the settings from disk, falling back to the defaults
+    fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                println!("[Backtest] Invalid config {}: {}, using defaults", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+// Everything written out at the end of a backtest
+#[derive(Serialize, Debug)]
+struct BacktestReport<'a> {
+    data: String,
+    records: usize,
+    start: u64,
+    end: u64,
+    clients: Vec<ClientPerformance>,
+    trades: &'a [TradeLogEntry],
+}
+
+// The strategies and everything their orders pass through on the way to the simulated fills
+struct Backtest {
+    strategies: Population,
+    router: Router,
+    orders: mpsc::Receiver<Order>,
+    stock_prices: Arc<Mutex<HashMap<String, Quote>>>,
+    exchange: SimulatedExchange,
+    portfolio: Portfolio,
+    margin: MarginConfig,
+    performance: Performance,
+    next_order_id: Arc<Mutex<u32>>, // Shared with the brokers for their algos' child orders
+}
+
+impl Backtest {
+    // Hand events to the strategies and route what they send until nothing more comes back
+    fn run(&mut self, now: u64, events: Vec<MarketEvent>, timer: bool) {
+        let mut pending: VecDeque<MarketEvent> = events.into();
+        let mut signals: Vec<(u32, Signal)> = Vec::new();
+        if timer {
+            pending.extend(self.router.on_timer(now).into_iter().map(MarketEvent::Execution));
+            let quotes = self.stock_prices.lock().unwrap().clone();
+            for (client_id, strategy) in self.strategies.iter_mut() {
+                signals.extend(strategy.on_timer(&quotes).into_iter().map(|signal| (*client_id, signal)));
+            }
+        }
+
+        loop {
+            while let Some(event) = pending.pop_front() {
+                signals.extend(dispatch(&mut self.strategies, &event));
+            }
+
+            // The trader's routing policy picks the broker
+            for (client_id, signal) in signals.drain(..) {
+                match signal {
+                    Signal::Submit(request) => {
+                        let order = new_order(
+                            client_id,
+                            request,
+                            &self.next_order_id,
+                            &self.portfolio.client(client_id),
+                            &self.stock_prices.lock().unwrap(),
+                            &self.margin,
+                        );
+                        match order {
+                            Ok(order) => self.router.submit(order),
+                            // Never sent, so the strategy stops waiting for it
+                            Err(rejected) => pending.push_back(MarketEvent::Execution(*rejected)),
+                        }
+                    }
+                    Signal::Cancel { order_id, stock } => self.router.cancel(order_id, client_id, &stock),
+                }
+            }
+
+            // Done once neither the strategies nor the brokers' algos have sent anything more
+            let orders: Vec<Order> = self.orders.try_iter().collect();
+            if orders.is_empty() && pending.is_empty() {
+                break;
+            }
+            let quotes = self.stock_prices.lock().unwrap().clone();
+            for order in orders {
+                let reports = self.exchange.submit(order, &quotes);
+                pending.extend(self.apply_reports(now, reports));
+            }
+        }
+    }
+
+    fn apply_reports(&mut self, now: u64, reports: Vec<ExecutionReport>) -> Vec<MarketEvent> {
+        let mut events = Vec::new();
+        for report in reports {
+            if report.status == "Filled" {
+                self.performance.record_fill(now, &report);
+            }
+            self.portfolio.apply(&report);
+            // The strategies see their algo orders as the parents they sent
+            events.extend(self.router.on_execution(report).into_iter().map(MarketEvent::Execution));
+        }
+        events
+    }
+
+    fn sample_equity(&mut self) {
+        let prices = self.stock_prices.lock().unwrap().clone();
+        for (client_id, _) in &self.strategies {
+            let equity = margin::status(&self.portfolio.client(*client_id), &prices, &self.margin).equity;
+            self.performance.sample(*client_id, equity);
+        }
+    }
+}
+
+fn main() {
+    let mut config = BacktestConfig::load(BACKTEST_PATH);
+    // The data file can also be given on the command line
+    if let Some(path) = env::args().nth(1) {
+        config.data = path;
+    }
+
+    let loaded = if config.data.ends_with(".csv") { load_csv(&config.data) } else { load_recording(&config.data) };
+    let records = match loaded {
+        Ok(records) if !records.is_empty() => records,
+        Ok(_) => {
+            println!("[Backtest] No market data in {}", config.data);
+            return;
+        }
+        Err(err) => {
+            println!("[Backtest] Failed to read {}: {}", config.data, err);
+            return;
+        }
+    };
+    let start = records[0].timestamp();
+    let end = records[records.len() - 1].timestamp();
+    println!("[Backtest] Replaying {} records from {}", records.len(), config.data);
+
+    let margin = MarginConfig::load(MARGIN_PATH);
+    let routing = RoutingConfig::load(ROUTING_PATH);
+    let fees = FeeSchedules::load(FEES_PATH);
+    let stock_prices = Arc::new(Mutex::new(HashMap::new()));
+    let (sender, receiver) = mpsc::channel::<Order>();
+    let next_order_id = Arc::new(Mutex::new(0));
+    let strategies: Population = load_population(STRATEGIES_PATH).iter().map(|spec| spec.build()).collect();
+    for (client_id, strategy) in &strategies {
+        println!("[Backtest] Client {} trades with {}", client_id, strategy.name());
+    }
+
+    let mut backtest = Backtest {
+        performance: Performance::new(
+            strategies.iter().map(|(client_id, strategy)| (*client_id, strategy.name().to_string())).collect(),
+        ),
+        strategies,
+        // Every broker's queue leads to the one simulated exchange
+        router: Router::new(
+            routing.clone(),
+            fees.clone(),
+            (1..=routing.broker_count.max(1))
+                .map(|id| Broker::new(id, sender.clone(), Arc::clone(&stock_prices), Arc::clone(&next_order_id)))
+                .collect(),
+        ),
+        orders: receiver,
+        stock_prices,
+        exchange: SimulatedExchange::new(config.fill_model.clone(), fees),
+        portfolio: Portfolio::new(margin.starting_cash),
+        margin,
+        next_order_id,
+    };
+
+    // Strategy timers and equity samples run on the data's clock, not the wall clock
+    let mut next_tick = start;
+    let mut next_sample = start;
+    for record in &records {
+        let now = record.timestamp();
+        if now >= next_sample {
+            backtest.sample_equity();
+            next_sample = now + config.sample_secs.max(1) * 1000;
+        }
+        let timer = now >= next_tick;
+        if timer {
+            next_tick = now + 1000;
+        }
+
+        let events = match record {
+            MarketRecord::Quote { stock, last, bid, ask, .. } => {
+                let quote = Quote { last: *last, bid: *bid, ask: *ask };
+                backtest.stock_prices.lock().unwrap().insert(stock.clone(), quote.clone());
+                let fills = backtest.exchange.on_quote(stock, &quote);
+                let mut events = vec![MarketEvent::Quote(stock.clone(), quote)];
+                events.extend(backtest.apply_reports(now, fills));
+                events
+            }
+            MarketRecord::Trade(trade) => {
+                backtest.router.on_trade(trade);
+                vec![MarketEvent::Trade(trade.clone())]
+            }
+        };
+        backtest.run(now, events, timer);
+    }
+
+    // Orders still working when the data ends never fill
+    let expired = backtest.exchange.expire_all();
+    let events = backtest.apply_reports(end, expired);
+    backtest.run(end, events, false);
+    backtest.sample_equity();
+
+    let clients = backtest.performance.summary(config.periods_per_year);
+    println!("\n==================== Backtest Results ====================");
+    for client in &clients {
+        println!(
+            "[Backtest] Client {} ({}): Return {:.2}%, Sharpe {:.2}, Max Drawdown {:.2}%, Turnover {:.2}x, Trades {}, Fees {:.2}",
+            client.client_id,
+            client.strategy,
+            client.total_return * 100.0,
+            client.sharpe_ratio,
+            client.max_drawdown * 100.0,
+            client.turnover,
+            client.trades,
+            client.fees
+        );
+    }
+
+    let report = BacktestReport {
+        data: config.data.clone(),
+        records: records.len(),
+        start,
+        end,
+        clients,
+        trades: backtest.performance.trades(),
+    };
+    let written = Path::new(&config.report_path)
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| {
+            let contents = serde_json::to_string_pretty(&report).map_err(std::io::Error::other)?;
+            fs::write(&config.report_path, contents)
+        });
+    match written {
+        Ok(()) => println!("[Backtest] Report with {} trades written to {}", report.trades.len(), config.report_path),
+        Err(err) => println!("[Backtest] Failed to write report to {}: {}", config.report_path, err),
+    }
+}
@@ -0,0 +1,175 @@
+use crate::brokers::Order;
+use crate::market_data::ExecutionReport;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Where each broker's blotter is written at the end of the day
+pub const BLOTTER_DIR: &str = "reports";
+
+// Where an order is in its life at the broker
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OrderState {
+    New,             // Taken by the broker, not sent yet
+    PendingTrigger,  // A stop held at the broker until its price trades
+    Sent,            // On its way to or working at the stock system
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+    Expired,
+}
+
+impl OrderState {
+    // Transitions the broker can observe; anything else is a report arriving out of order
+    pub fn can_move_to(self, next: OrderState) -> bool {
+        use OrderState::*;
+        match self {
+            New => matches!(next, PendingTrigger | Sent | Cancelled | Rejected),
+            PendingTrigger => matches!(next, Sent | Cancelled | Rejected | Expired),
+            Sent | PartiallyFilled => {
+                matches!(next, PartiallyFilled | Filled | Cancelled | Rejected | Expired)
+            }
+            Filled | Cancelled | Rejected | Expired => false,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Transition {
+    pub state: OrderState,
+    pub timestamp: u64, // Milliseconds since the Unix epoch
+    pub note: Option<String>,
+}
+
+// One order on the blotter
+#[derive(Serialize, Debug, Clone)]
+pub struct BlotterEntry {
+    pub order: Order,
+    pub parent_id: Option<u32>, // The algo or group a child order was sent for
+    pub state: OrderState,
+    pub quantity: u32, // Shares the order can trade; a bracket its entry, its exits being orders of their own
+    pub open: u32,     // Not yet filled, cancelled, rejected or expired
+    pub filled: u32,
+    pub average_price: f64,
+    pub fees: f64,
+    pub transitions: Vec<Transition>,
+}
+
+impl BlotterEntry {
+    fn enter(&mut self, state: OrderState, timestamp: u64, note: Option<String>) {
+        if state == self.state {
+            return;
+        }
+        if !self.state.can_move_to(state) {
+            println!(
+                "[Blotter] Order {}: ignoring {:?} -> {:?}",
+                self.order.order_id, self.state, state
+            );
+            return;
+        }
+        self.state = state;
+        self.transitions.push(Transition { state, timestamp, note });
+    }
+}
+
+// Every order a broker has handled today, with its full history
+#[derive(Debug, Default)]
+pub struct Blotter {
+    entries: BTreeMap<u32, BlotterEntry>,
+}
+
+impl Blotter {
+    // A new order in its first state. A held stop that is now going out keeps its entry and its history,
+    // taking on the quantity it is sent for.
+    pub fn record(&mut self, order: &Order, quantity: u32, parent_id: Option<u32>, state: OrderState, timestamp: u64) {
+        if let Some(entry) = self.entries.get_mut(&order.order_id) {
+            if entry.state == OrderState::PendingTrigger {
+                entry.order = order.clone();
+                entry.quantity = quantity;
+                entry.open = quantity;
+            }
+            return;
+        }
+        let entry = BlotterEntry {
+            order: order.clone(),
+            parent_id,
+            state,
+            quantity,
+            open: quantity,
+            filled: 0,
+            average_price: 0.0,
+            fees: 0.0,
+            transitions: vec![Transition { state, timestamp, note: None }],
+        };
+        self.entries.insert(order.order_id, entry);
+    }
+
+    pub fn transition(&mut self, order_id: u32, state: OrderState, timestamp: u64, note: Option<String>) {
+        if let Some(entry) = self.entries.get_mut(&order_id) {
+            entry.enter(state, timestamp, note);
+        }
+    }
+
+    pub fn state(&self, order_id: u32) -> Option<OrderState> {
+        self.entries.get(&order_id).map(|entry| entry.state)
+    }
+
+    // Move an order along from one of its execution reports. An order split across venues only ends
+    // once every part of it has.
+    pub fn on_report(&mut self, report: &ExecutionReport, timestamp: u64) {
+        let Some(entry) = self.entries.get_mut(&report.order_id) else { return };
+        match report.status.as_str() {
+            "Filled" => {
+                let notional = entry.average_price * entry.filled as f64 + report.price * report.quantity as f64;
+                entry.filled += report.quantity;
+                entry.average_price = notional / entry.filled.max(1) as f64;
+                entry.fees += report.fee;
+                entry.open = entry.open.saturating_sub(report.quantity);
+                let state = if entry.open == 0 { OrderState::Filled } else { OrderState::PartiallyFilled };
+                entry.enter(state, timestamp, None);
+            }
+            "Cancelled" | "Rejected" | "Expired" => {
+                entry.open = entry.open.saturating_sub(report.quantity);
+                if entry.open > 0 {
+                    return;
+                }
+                let state = match report.status.as_str() {
+                    "Cancelled" => OrderState::Cancelled,
+                    "Rejected" => OrderState::Rejected,
+                    _ => OrderState::Expired,
+                };
+                entry.enter(state, timestamp, report.reason.clone());
+            }
+            _ => {}
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &BlotterEntry> {
+        self.entries.values()
+    }
+
+    pub fn for_client(&self, client_id: u32) -> impl Iterator<Item = &BlotterEntry> {
+        self.entries().filter(move |entry| entry.order.client_id == client_id)
+    }
+
+    // How many orders are in each state
+    pub fn counts<'a>(entries: impl Iterator<Item = &'a BlotterEntry>) -> BTreeMap<OrderState, usize> {
+        let mut counts = BTreeMap::new();
+        for entry in entries {
+            *counts.entry(entry.state).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    pub fn export(&self, path: &Path) -> io::Result<()> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let entries: Vec<&BlotterEntry> = self.entries().collect();
+        let contents = serde_json::to_string_pretty(&entries).map_err(io::Error::other)?;
+        fs::write(path, contents)
+    }
+}
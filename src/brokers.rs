@@ -0,0 +1,544 @@
+use crate::algos::{is_algo, AlgoParams, ParentOrder};
+use crate::blotter::{Blotter, OrderState};
+use crate::history::now_millis;
+use crate::market_data::{ExecutionReport, Quote, TradePrint};
+use crate::order_groups::{is_group, Leg, LinkedPrices, OrderGroup};
+use crate::smart_router::SmartOrderRouter;
+use serde::{Deserialize, Serialize};
+use std::sync::{mpsc, Arc, Mutex};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+
+// Struct for Order
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Order {
+    pub order_id: u32,
+    #[serde(default)]
+    pub client_id: u32, // Account the order trades for
+    #[serde(default)]
+    pub broker_id: u32, // Broker the order was sent through, whose fee schedule applies
+    #[serde(default)]
+    pub session: String, // Trader session the order comes from; `order_id` is only unique within it
+    pub stock: String,
+    pub action: String, // "Buy", "Sell" (long sale), "SellShort" or "BuyToCover"
+    pub quantity: u32,
+    pub price: f64,     // For market orders, price = 0
+    pub order_type: String, // "Market", "Limit", "Cancel" to withdraw the working order `order_id`, an algo or a group
+    #[serde(default = "default_time_in_force")]
+    pub time_in_force: String, // "Day" or "GTC"; GTC limit orders carry over to the next session
+    #[serde(default)]
+    pub venue: String, // Venue the order is sent to; empty for the primary venue
+    #[serde(default)]
+    pub algo: Option<AlgoParams>, // How the broker works a "TWAP", "VWAP", "Iceberg" or "POV" order
+    #[serde(default)]
+    pub links: Option<LinkedPrices>, // Exit prices of an "OCO" or "Bracket" group
+}
+
+impl Order {
+    // A report on an order the broker manages itself, as its client sees it
+    pub fn report(&self, status: &str, quantity: u32, price: f64, reason: Option<String>) -> ExecutionReport {
+        ExecutionReport {
+            order_id: self.order_id,
+            client_id: self.client_id,
+            stock: self.stock.clone(),
+            action: self.action.clone(),
+            status: status.to_string(),
+            quantity,
+            price,
+            reason,
+            broker_id: self.broker_id,
+            fee: 0.0,
+            liquidity: None,
+            venue: String::new(),
+            session: self.session.clone(),
+            exchange_id: String::new(),
+        }
+    }
+}
+
+fn default_time_in_force() -> String {
+    "Day".to_string()
+}
+
+// Orders through one broker since it started
+#[derive(Debug, Clone)]
+pub struct BrokerStats {
+    pub routed: u64,    // Orders and cancels handed to the broker
+    pub forwarded: u64, // Taken off the broker's queue and sent on to the stock system
+    pub shares: u64,
+    pub started: Instant,
+}
+
+impl BrokerStats {
+    // Waiting in the broker's queue
+    pub fn queued(&self) -> u64 {
+        self.routed.saturating_sub(self.forwarded)
+    }
+
+    // Orders forwarded per second
+    pub fn throughput(&self) -> f64 {
+        self.forwarded as f64 / self.started.elapsed().as_secs_f64().max(1.0)
+    }
+}
+
+// Struct for Broker
+pub struct Broker {
+    pub id: u32,
+    pub session: String,             // Trader session stamped on every order, so the stock system can tell traders apart
+    pub blotter: Blotter,            // Every order the broker has handled today and the states it went through
+    pub sender: mpsc::Sender<Order>, // The broker's own queue to the stock system
+    pub stock_prices: Arc<Mutex<HashMap<String, Quote>>>, // Shared stock quotes
+    pub stats: Arc<Mutex<BrokerStats>>, // Shared with whatever drains the queue
+    pub smart_router: Option<SmartOrderRouter>, // Splits orders across venues; without it everything goes to the primary venue
+    venues_by_order: HashMap<u32, Vec<String>>, // Where each order was sent, so a cancel reaches every part of it
+    order_ids: Arc<Mutex<u32>>, // Shared with the clients, so child orders get ids of their own
+    algos: BTreeMap<u32, ParentOrder>, // Parent orders being worked, by order id
+    groups: BTreeMap<u32, OrderGroup>, // OCO pairs and brackets being managed, by order id
+    parents: HashMap<u32, u32>, // Child order id to its algo's or group's
+    reports: Vec<ExecutionReport>, // About algos, groups and refused orders, waiting to be handed to the clients
+    clock: u64, // Time of the last timer tick, in milliseconds since the Unix epoch
+}
+
+impl Broker {
+    // Create a new Broker
+    pub fn new(
+        id: u32,
+        sender: mpsc::Sender<Order>,
+        stock_prices: Arc<Mutex<HashMap<String, Quote>>>,
+        order_ids: Arc<Mutex<u32>>,
+    ) -> Self {
+        Self {
+            id,
+            session: String::new(),
+            blotter: Blotter::default(),
+            sender,
+            stock_prices,
+            stats: Arc::new(Mutex::new(BrokerStats { routed: 0, forwarded: 0, shares: 0, started: Instant::now() })),
+            smart_router: None,
+            venues_by_order: HashMap::new(),
+            order_ids,
+            algos: BTreeMap::new(),
+            groups: BTreeMap::new(),
+            parents: HashMap::new(),
+            reports: Vec::new(),
+            clock: 0,
+        }
+    }
+
+    pub fn with_session(mut self, session: &str) -> Self {
+        self.session = session.to_string();
+        self
+    }
+
+    pub fn with_smart_router(mut self, smart_router: SmartOrderRouter) -> Self {
+        self.smart_router = Some(smart_router);
+        self
+    }
+
+    pub fn handle_order(&mut self, mut order: Order) {
+        order.broker_id = self.id;
+        order.session = self.session.clone();
+
+        if is_algo(&order.order_type) {
+            self.start_algo(order);
+        } else if is_group(&order.order_type) {
+            self.start_group(order);
+        } else {
+            self.route(order, None);
+        }
+    }
+
+    fn route(&mut self, order: Order, parent_id: Option<u32>) {
+        self.blotter.record(&order, order.quantity, parent_id, OrderState::New, now_millis());
+        let children = match &self.smart_router {
+            Some(smart_router) => smart_router.split(&order),
+            None => vec![order],
+        };
+        if children.len() > 1 {
+            let parts: Vec<String> = children.iter().map(|child| format!("{} x{}", child.venue, child.quantity)).collect();
+            println!("[Smart Router] Order {} split across venues: {}", children[0].order_id, parts.join(", "));
+        }
+        for child in children {
+            self.venues_by_order.entry(child.order_id).or_default().push(child.venue.clone());
+            self.process_order(child);
+        }
+    }
+
+    fn process_order(&mut self, order: Order) {
+        let order_id = order.order_id;
+        match order.order_type.as_str() {
+            "Market" => {
+                println!(
+                    "[Broker] Processing Market Order: Stock: {}, Action: {}, Quantity: {}, Price: {:.2}",
+                    order.stock, order.action, order.quantity, order.price
+                );
+                self.process_market_order(order);
+            }
+            "Limit" => {
+                println!(
+                    "[Broker] Processing Limit Order: Stock: {}, Action: {}, Quantity: {}, Limit Price: {:.2}",
+                    order.stock, order.action, order.quantity, order.price
+                );
+                self.process_limit_order(order);
+            }
+            _ => {
+                println!("[Broker] Unknown order type: {:?}", order);
+                // Reported back like the broker's own orders; taking the report moves the blotter to Rejected
+                let reason = format!("unknown order type {}", order.order_type);
+                self.reports.push(order.report("Rejected", order.quantity, order.price, Some(reason)));
+                return;
+            }
+        }
+        self.mark_sent(order_id);
+    }
+
+    // The first time an order, or a child of an algo or group, goes out
+    fn mark_sent(&mut self, order_id: u32) {
+        if matches!(self.blotter.state(order_id), Some(OrderState::New | OrderState::PendingTrigger)) {
+            self.blotter.transition(order_id, OrderState::Sent, now_millis(), None);
+        }
+    }
+
+    pub fn cancel_order(&mut self, order_id: u32, client_id: u32, stock: &str) {
+        if self.algos.contains_key(&order_id) {
+            self.wind_up(order_id, "Cancelled", "cancelled by client");
+            self.settle(order_id);
+        } else if self.groups.contains_key(&order_id) {
+            self.wind_up_group(order_id, "Cancelled", "cancelled by client");
+            self.settle_group(order_id);
+        } else {
+            self.send_cancel(order_id, client_id, stock);
+        }
+    }
+
+    // Ask every venue holding part of a working order to withdraw it
+    fn send_cancel(&mut self, order_id: u32, client_id: u32, stock: &str) {
+        let venues = self.venues_by_order.get(&order_id).cloned().unwrap_or_else(|| vec![String::new()]);
+        for venue in venues {
+            let cancel = Order {
+                order_id,
+                client_id,
+                broker_id: self.id,
+                session: self.session.clone(),
+                stock: stock.to_string(),
+                action: "Cancel".to_string(),
+                quantity: 0,
+                price: 0.0,
+                order_type: "Cancel".to_string(),
+                time_in_force: default_time_in_force(),
+                venue,
+                algo: None,
+                links: None,
+            };
+            println!("[Broker] Cancel Requested: Order {}, Client {}, Stock {}", order_id, client_id, stock);
+            self.count(&cancel);
+
+            self.sender
+                .send(cancel)
+                .expect("Failed to send cancel to stock system");
+        }
+    }
+
+    fn process_market_order(&self, mut order: Order) {
+        // Fetch the current market price from the side of the book the order will trade against
+        let current_price = {
+            let prices = self.stock_prices.lock().unwrap();
+            prices.get(&order.stock).map(|q| q.price_for(&order.action)).unwrap_or(0.0)
+        };
+    
+        // Set the current market price
+        order.price = current_price;
+    
+        println!("-----------------------------[Market Order]-------------------------------\n");
+        println!(
+            "[Market Order Executed] Stock: {}, Action: {}, Quantity: {}, Price: {:.2}\n",
+            order.stock, order.action, order.quantity, order.price
+        );
+    
+        // Clone the order before sending
+        let order_to_send = order.clone();
+        self.count(&order);
+    
+        // Send the market order to the stock system
+        self.sender
+            .send(order_to_send)
+            .expect("Failed to send market order to stock system");
+    
+        println!(
+            "[Stock System] Market Order Sent: Stock: {}, Action: {}, Quantity: {}, Price: {:.2}, Type: Market\n",
+            order.stock, order.action, order.quantity, order.price
+        );
+    }
+    
+    fn process_limit_order(&self, order: Order) {
+        println!("\n--------------------------------------------------------------------------\n");
+        println!(
+            "[Limit Order Received]\n  Stock: {}\n  Action: {}\n  Quantity: {}\n  Limit Price: {:.2}",
+            order.stock, order.action, order.quantity, order.price
+        );
+
+        self.count(&order);
+        // Limit orders rest in the stock system's order book until they can execute
+        self.sender
+            .send(order.clone())
+            .expect("Failed to send limit order to stock system");
+
+        println!(
+            "[Stock System] Order Sent: Stock: {}, Action: {}, Quantity: {}, Limit: {:.2}, Type: Limit\n--------------------------------------------------------------------------\n",
+            order.stock, order.action, order.quantity, order.price
+        );
+    }
+
+    // Take on a parent order; its window starts and its first child goes out on the next timer tick
+    fn start_algo(&mut self, order: Order) {
+        let parent = ParentOrder::new(order);
+        println!("[Algo] Broker {} working {}", self.id, parent.progress());
+        self.blotter.record(&parent.order, parent.order.quantity, None, OrderState::New, now_millis());
+        let accepted = parent.order.report("Accepted", parent.order.quantity, parent.order.price, None);
+        self.reports.push(accepted);
+        self.algos.insert(parent.order.order_id, parent);
+    }
+
+    // Release the child orders the algos have due and wind up those whose window has passed
+    pub fn on_timer(&mut self, now: u64) {
+        self.clock = now;
+        let parent_ids: Vec<u32> = self.algos.keys().copied().collect();
+        for parent_id in parent_ids {
+            let Some(parent) = self.algos.get_mut(&parent_id) else { continue };
+            parent.tick(now);
+            let expired = parent.finishing.is_none() && parent.is_expired(now) && parent.winds_up_at_expiry();
+            if expired {
+                self.wind_up(parent_id, "Expired", "algo window ended");
+            }
+            self.work(parent_id);
+        }
+
+        // Stops are held here and watched against the latest quotes
+        let group_ids: Vec<u32> = self.groups.keys().copied().collect();
+        for group_id in group_ids {
+            let last = {
+                let prices = self.stock_prices.lock().unwrap();
+                self.groups.get(&group_id).and_then(|group| prices.get(&group.order.stock)).map(|quote| quote.last)
+            };
+            if let Some(last) = last {
+                self.check_stop(group_id, last);
+            }
+            self.work_group(group_id);
+        }
+    }
+
+    // Market volume for participation algos
+    pub fn on_trade(&mut self, trade: &TradePrint) {
+        for parent in self.algos.values_mut().filter(|parent| parent.order.stock == trade.stock) {
+            parent.on_trade(trade.quantity);
+        }
+    }
+
+    // Reports as the client should see them: those of child orders become reports of their parent
+    pub fn on_execution(&mut self, report: ExecutionReport) -> Vec<ExecutionReport> {
+        self.blotter.on_report(&report, now_millis());
+        if let Some(smart_router) = self.smart_router.as_mut().filter(|_| report.status == "Filled") {
+            smart_router.on_fill(&report);
+        }
+        let Some(parent_id) = self.parents.get(&report.order_id).copied() else {
+            return vec![report];
+        };
+        let rejected = (report.status == "Rejected")
+            .then(|| format!("child order rejected: {}", report.reason.as_deref().unwrap_or("-")));
+        // Reports on a bracket's exits, already on the blotter under their own ids
+        let mut exits = Vec::new();
+        if let Some(parent) = self.algos.get_mut(&parent_id) {
+            if let Some(fill) = parent.on_child_report(&report) {
+                println!("[Algo] Broker {} {}", self.id, parent.progress());
+                self.reports.push(fill);
+            }
+            if let Some(reason) = rejected.filter(|_| parent.finishing.is_none()) {
+                self.wind_up(parent_id, "Cancelled", &reason);
+            }
+            self.work(parent_id);
+        } else if let Some(group) = self.groups.get_mut(&parent_id) {
+            if let Some(update) = group.on_child_report(&report) {
+                println!("[{}] Broker {} {}", group.order.order_type, self.id, group.progress());
+                if update.order_id == parent_id {
+                    self.reports.push(update);
+                } else {
+                    exits.push(update);
+                }
+            }
+            if let Some(reason) = rejected.filter(|_| group.finishing.is_none()) {
+                self.wind_up_group(parent_id, "Cancelled", &reason);
+            }
+            self.work_group(parent_id);
+        }
+        let mut reports = self.take_reports();
+        reports.extend(exits);
+        reports
+    }
+
+    // Reports about parent orders and refused orders since the last call
+    pub fn take_reports(&mut self) -> Vec<ExecutionReport> {
+        let reports = std::mem::take(&mut self.reports);
+        for report in &reports {
+            self.blotter.on_report(report, now_millis());
+        }
+        reports
+    }
+
+    // Send the parent's next child order if one is due
+    fn work(&mut self, parent_id: u32) {
+        let now = self.clock;
+        let Some(parent) = self.algos.get_mut(&parent_id) else { return };
+        let quantity = parent.due(now);
+        if quantity > 0 {
+            let child_id = next_id(&self.order_ids);
+            let child = parent.child(child_id, quantity);
+            println!("[Algo] Broker {} sends child order {} x{} of {}", self.id, child_id, quantity, parent.progress());
+            self.parents.insert(child_id, parent_id);
+            self.route(child, Some(parent_id));
+            self.mark_sent(parent_id);
+        }
+        self.settle(parent_id);
+    }
+
+    // Stop releasing children and withdraw those still working
+    fn wind_up(&mut self, parent_id: u32, status: &str, reason: &str) {
+        let Some(parent) = self.algos.get_mut(&parent_id) else { return };
+        parent.finishing = Some((status.to_string(), reason.to_string()));
+        let (client_id, stock) = (parent.order.client_id, parent.order.stock.clone());
+        for child_id in parent.working_children() {
+            self.send_cancel(child_id, client_id, &stock);
+        }
+    }
+
+    // Forget the children of an algo or group that is done with
+    fn forget_children(&mut self, parent_id: u32) {
+        let venues_by_order = &mut self.venues_by_order;
+        self.parents.retain(|child_id, parent| {
+            if *parent == parent_id {
+                venues_by_order.remove(child_id);
+            }
+            *parent != parent_id
+        });
+    }
+
+    // Drop a parent once nothing more will trade, telling the client what happened to the rest
+    fn settle(&mut self, parent_id: u32) {
+        if !self.algos.get(&parent_id).is_some_and(ParentOrder::is_done) {
+            return;
+        }
+        let Some(parent) = self.algos.remove(&parent_id) else { return };
+        self.forget_children(parent_id);
+        match &parent.finishing {
+            Some((status, reason)) if parent.remaining() > 0 => {
+                println!("[Algo] Broker {} {} {}: {}", self.id, status, parent.progress(), reason);
+                let report =
+                    parent.order.report(status, parent.remaining(), parent.average_price(), Some(reason.clone()));
+                self.reports.push(report);
+            }
+            _ => println!("[Algo] Broker {} completed {}", self.id, parent.progress()),
+        }
+    }
+
+    // Take on an OCO pair or a bracket and send its first legs
+    fn start_group(&mut self, order: Order) {
+        let mut group = OrderGroup::new(order);
+        let group_id = group.order.order_id;
+        println!("[{}] Broker {} managing {}", group.order.order_type, self.id, group.progress());
+        let now = now_millis();
+        self.blotter.record(&group.order, group.order.quantity, None, OrderState::New, now);
+        // The stop waits here for its price, so it is on the blotter from the start
+        if group.has_stop() {
+            let stop = group.stop_order(next_id(&self.order_ids));
+            self.blotter.record(&stop, stop.quantity, Some(group_id), OrderState::PendingTrigger, now);
+            group.held_stop = Some(stop.order_id);
+        }
+        let accepted = group.order.report("Accepted", group.order.quantity, group.order.price, None);
+        self.reports.push(accepted);
+        self.groups.insert(group_id, group);
+        self.work_group(group_id);
+    }
+
+    // Once the stop price trades, withdraw the entry and take-profit; the stop goes out when they are gone
+    fn check_stop(&mut self, group_id: u32, last: f64) {
+        let Some(group) = self.groups.get_mut(&group_id) else { return };
+        let Some(withdraw) = group.check_stop(last) else { return };
+        println!("[{}] Broker {} stop reached at {:.2}: {}", group.order.order_type, self.id, last, group.progress());
+        let (client_id, stock) = (group.order.client_id, group.order.stock.clone());
+        for child_id in withdraw {
+            self.send_cancel(child_id, client_id, &stock);
+        }
+    }
+
+    // Send whatever legs the group has due
+    fn work_group(&mut self, group_id: u32) {
+        let Some(group) = self.groups.get(&group_id) else { return };
+        for (leg, quantity) in group.due() {
+            let Some(group) = self.groups.get_mut(&group_id) else { return };
+            let child_id = match leg {
+                Leg::StopLoss => group.held_stop.take(),
+                _ => None,
+            }
+            .unwrap_or_else(|| next_id(&self.order_ids));
+            let own_order = group.is_own_order(leg);
+            let child = group.child(leg, child_id, quantity);
+            println!(
+                "[{}] Broker {} sends {:?} order {} {} x{} @ {:.2}",
+                group.order.order_type, self.id, leg, child_id, child.action, quantity, child.price
+            );
+            if own_order {
+                self.reports.push(child.report("Accepted", quantity, child.price, None));
+            }
+            self.parents.insert(child_id, group_id);
+            self.route(child, Some(group_id));
+            self.mark_sent(group_id);
+        }
+        self.settle_group(group_id);
+    }
+
+    fn wind_up_group(&mut self, group_id: u32, status: &str, reason: &str) {
+        let Some(group) = self.groups.get_mut(&group_id) else { return };
+        group.finishing = Some((status.to_string(), reason.to_string()));
+        let (client_id, stock) = (group.order.client_id, group.order.stock.clone());
+        for child_id in group.working_children() {
+            self.send_cancel(child_id, client_id, &stock);
+        }
+    }
+
+    // Drop a group once nothing more will trade, telling the client about any shares that never will
+    fn settle_group(&mut self, group_id: u32) {
+        if !self.groups.get(&group_id).is_some_and(OrderGroup::is_done) {
+            return;
+        }
+        let Some(group) = self.groups.remove(&group_id) else { return };
+        self.forget_children(group_id);
+        if let Some(stop_id) = group.held_stop {
+            let reason = group.finishing.as_ref().map_or("linked leg completed", |(_, reason)| reason.as_str());
+            self.blotter.transition(stop_id, OrderState::Cancelled, now_millis(), Some(reason.to_string()));
+        }
+        let kind = &group.order.order_type;
+        if group.remaining() == 0 {
+            println!("[{}] Broker {} completed {}", kind, self.id, group.progress());
+            return;
+        }
+        let (status, reason) = group
+            .finishing
+            .clone()
+            .unwrap_or_else(|| ("Cancelled".to_string(), "legs ended without trading".to_string()));
+        println!("[{}] Broker {} {} {}: {}", kind, self.id, status, group.progress(), reason);
+        self.reports.push(group.order.report(&status, group.remaining(), 0.0, Some(reason)));
+    }
+
+    // Every order or cancel put on the broker's queue
+    fn count(&self, order: &Order) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.routed += 1;
+        stats.shares += order.quantity as u64;
+    }
+}
+
+// Next id from the counter the clients' orders are numbered from
+fn next_id(order_ids: &Mutex<u32>) -> u32 {
+    let mut id = order_ids.lock().unwrap();
+    *id += 1;
+    *id
+}
@@ -0,0 +1,235 @@
+use crate::market_data::SettlementReport;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+// Default location of the clearing terms
+pub const CLEARING_PATH: &str = "config/clearing.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ClearingConfig {
+    pub settlement_days: u32, // Trades settle this many trading days after the trade date (T+N)
+}
+
+impl Default for ClearingConfig {
+    fn default() -> Self {
+        Self { settlement_days: 2 }
+    }
+}
+
+impl ClearingConfig {
+    // Load the terms from disk, falling back to the defaults
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                println!("[Clearing] Invalid config {}: {}, using defaults", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+// One side of a fill as it reaches clearing
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClearedTrade {
+    pub client_id: u32,
+    pub broker_id: u32,
+    pub stock: String,
+    pub shares: i64,      // Received (+) or delivered (-), including borrowed shares
+    pub long_shares: i64, // Part of `shares` that moves the client's own holdings
+    pub cash: f64,        // Received (+) or paid (-), after fees
+}
+
+// What one client owes or is owed within a broker's obligation
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Allocation {
+    pub long_shares: i64,
+    pub cash: f64,
+}
+
+// Net shares and cash a broker receives (+) or delivers (-) in a symbol on the settlement date
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Obligation {
+    pub broker_id: u32,
+    pub stock: String,
+    pub trade_date: String,
+    pub settlement_date: String,
+    pub net_shares: i64,
+    pub net_cash: f64,
+    pub allocations: BTreeMap<u32, Allocation>, // By client; only unsettled ones remain after a fail
+}
+
+impl Obligation {
+    pub fn report(&self, status: &str, failed_shares: u64) -> SettlementReport {
+        SettlementReport {
+            broker_id: self.broker_id,
+            stock: self.stock.clone(),
+            trade_date: self.trade_date.clone(),
+            settlement_date: self.settlement_date.clone(),
+            net_shares: self.net_shares,
+            net_cash: self.net_cash,
+            status: status.to_string(),
+            failed_shares,
+        }
+    }
+}
+
+// Today's trades and every obligation not yet settled
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Clearing {
+    trades: Vec<ClearedTrade>,
+    obligations: Vec<Obligation>,
+}
+
+impl Clearing {
+    pub fn record(&mut self, trade: ClearedTrade) {
+        self.trades.push(trade);
+    }
+
+    // Net the day's trades per broker and symbol into obligations due on `settlement_date`
+    pub fn net_trades(&mut self, trade_date: &str, settlement_date: &str) -> Vec<SettlementReport> {
+        let mut netted: BTreeMap<(u32, String), Obligation> = BTreeMap::new();
+        for trade in self.trades.drain(..) {
+            let obligation = netted.entry((trade.broker_id, trade.stock.clone())).or_insert_with(|| Obligation {
+                broker_id: trade.broker_id,
+                stock: trade.stock.clone(),
+                trade_date: trade_date.to_string(),
+                settlement_date: settlement_date.to_string(),
+                net_shares: 0,
+                net_cash: 0.0,
+                allocations: BTreeMap::new(),
+            });
+            obligation.net_shares += trade.shares;
+            obligation.net_cash += trade.cash;
+            let allocation = obligation.allocations.entry(trade.client_id).or_default();
+            allocation.long_shares += trade.long_shares;
+            allocation.cash += trade.cash;
+        }
+
+        let reports = netted.values().map(|obligation| obligation.report("Pending", 0)).collect();
+        self.obligations.extend(netted.into_values());
+        reports
+    }
+
+    // Obligations due on or before `date`, taken out to be settled
+    pub fn take_due(&mut self, date: &str) -> Vec<Obligation> {
+        let (due, pending) = std::mem::take(&mut self.obligations)
+            .into_iter()
+            .partition(|obligation| obligation.settlement_date.as_str() <= date);
+        self.obligations = pending;
+        due
+    }
+
+    // A failed obligation stays open with the allocations that could not settle
+    pub fn carry_over(&mut self, obligation: Obligation) {
+        self.obligations.push(obligation);
+    }
+
+    // Scale unsettled shares for a split
+    pub fn apply_split(&mut self, stock: &str, new_shares: u32, old_shares: u32) {
+        let scale = |shares: i64| shares * new_shares as i64 / old_shares as i64;
+        for trade in self.trades.iter_mut().filter(|t| t.stock == stock) {
+            trade.shares = scale(trade.shares);
+            trade.long_shares = scale(trade.long_shares);
+        }
+        for obligation in self.obligations.iter_mut().filter(|o| o.stock == stock) {
+            obligation.net_shares = scale(obligation.net_shares);
+            for allocation in obligation.allocations.values_mut() {
+                allocation.long_shares = scale(allocation.long_shares);
+            }
+        }
+    }
+
+    pub fn rename_symbol(&mut self, old: &str, new: &str) {
+        for trade in self.trades.iter_mut().filter(|t| t.stock == old) {
+            trade.stock = new.to_string();
+        }
+        for obligation in self.obligations.iter_mut().filter(|o| o.stock == old) {
+            obligation.stock = new.to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(client_id: u32, broker_id: u32, stock: &str, shares: i64, cash: f64) -> ClearedTrade {
+        ClearedTrade {
+            client_id,
+            broker_id,
+            stock: stock.to_string(),
+            shares,
+            long_shares: shares,
+            cash,
+        }
+    }
+
+    #[test]
+    fn trades_net_per_broker_and_symbol() {
+        let mut clearing = Clearing::default();
+        clearing.record(trade(1, 1, "AAPL", 100, -1000.0));
+        clearing.record(trade(2, 1, "AAPL", -40, 400.0));
+        clearing.record(trade(1, 1, "MSFT", 10, -200.0));
+        clearing.record(trade(3, 2, "AAPL", -60, 600.0));
+
+        let reports = clearing.net_trades("2024-01-02", "2024-01-04");
+
+        let netted: Vec<(u32, &str, i64, f64)> =
+            reports.iter().map(|r| (r.broker_id, r.stock.as_str(), r.net_shares, r.net_cash)).collect();
+        assert_eq!(netted, vec![(1, "AAPL", 60, -600.0), (1, "MSFT", 10, -200.0), (2, "AAPL", -60, 600.0)]);
+        assert!(reports.iter().all(|r| r.status == "Pending" && r.settlement_date == "2024-01-04"));
+    }
+
+    #[test]
+    fn allocations_keep_each_clients_share_of_the_net() {
+        let mut clearing = Clearing::default();
+        clearing.record(trade(1, 1, "AAPL", 100, -1000.0));
+        clearing.record(trade(2, 1, "AAPL", -40, 400.0));
+        clearing.record(trade(1, 1, "AAPL", -30, 310.0));
+        clearing.net_trades("2024-01-02", "2024-01-04");
+
+        let due = clearing.take_due("2024-01-04");
+
+        assert_eq!(due.len(), 1);
+        let allocations: Vec<(u32, i64, f64)> =
+            due[0].allocations.iter().map(|(client, a)| (*client, a.long_shares, a.cash)).collect();
+        assert_eq!(allocations, vec![(1, 70, -690.0), (2, -40, 400.0)]);
+    }
+
+    #[test]
+    fn obligations_wait_for_their_settlement_date() {
+        let mut clearing = Clearing::default();
+        clearing.record(trade(1, 1, "AAPL", 100, -1000.0));
+        clearing.net_trades("2024-01-02", "2024-01-04");
+        clearing.record(trade(1, 1, "AAPL", 50, -500.0));
+        clearing.net_trades("2024-01-03", "2024-01-05");
+
+        assert!(clearing.take_due("2024-01-03").is_empty());
+        let due = clearing.take_due("2024-01-04");
+        assert_eq!(due.iter().map(|o| o.net_shares).collect::<Vec<_>>(), vec![100]);
+
+        // A fail stays open and comes due again the next day
+        clearing.carry_over(due.into_iter().next().unwrap());
+        let due = clearing.take_due("2024-01-05");
+        assert_eq!(due.iter().map(|o| o.net_shares).collect::<Vec<_>>(), vec![50, 100]);
+    }
+
+    #[test]
+    fn splits_scale_unsettled_shares() {
+        let mut clearing = Clearing::default();
+        clearing.record(trade(1, 1, "AAPL", 100, -1000.0));
+        clearing.net_trades("2024-01-02", "2024-01-04");
+        clearing.record(trade(1, 1, "AAPL", 30, -300.0));
+
+        clearing.apply_split("AAPL", 2, 1);
+        let reports = clearing.net_trades("2024-01-03", "2024-01-05");
+
+        assert_eq!(reports[0].net_shares, 60);
+        let due = clearing.take_due("2024-01-05");
+        assert_eq!(due.iter().map(|o| o.net_shares).collect::<Vec<_>>(), vec![200, 60]);
+        assert_eq!(due[0].allocations[&1].long_shares, 200);
+    }
+}
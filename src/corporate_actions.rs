@@ -0,0 +1,103 @@
+use crate::accounts::Accounts;
+use crate::market_data::{split_shares, CorporateAction, CorporateActionKind};
+use crate::order_book::OrderBook;
+use crate::stock_data::Stock;
+use crate::trade_tape::TradeTape;
+use std::collections::HashMap;
+use std::fs;
+
+// Default location of the corporate action schedule
+pub const CORPORATE_ACTIONS_PATH: &str = "config/corporate_actions.json";
+
+// Load the scheduled corporate actions; none if the file is missing
+pub fn load_schedule(path: &str) -> Vec<CorporateAction> {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+            println!("[Corporate Action] Invalid schedule {}: {}, ignoring it", path, err);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Apply an action on its ex-date to the price, availability, resting orders and holdings of the symbol
+pub fn apply(
+    action: &CorporateAction,
+    stock_data: &mut [Stock],
+    books: &mut HashMap<String, OrderBook>,
+    accounts: &mut Accounts,
+    tape: &mut TradeTape,
+) {
+    let Some(stock) = stock_data.iter_mut().find(|s| s.name == action.stock) else {
+        println!("[Corporate Action] Stock not found: {}", action.stock);
+        return;
+    };
+    let book = books.entry(stock.name.clone()).or_default();
+
+    match &action.kind {
+        CorporateActionKind::CashDividend { amount } => {
+            let close = stock.price;
+            stock.price = (stock.price - amount).max(0.01);
+            book.apply_dividend(*amount);
+            let paid = accounts.apply_dividend(&stock.name, *amount);
+
+            println!(
+                "[Corporate Action] Stock: {}, Cash Dividend: {:.2}/share, Price: {:.2} -> {:.2}, Paid to Holders: {:.2}",
+                stock.name, amount, close, stock.price, paid
+            );
+        }
+        CorporateActionKind::Split { new_shares, old_shares } => {
+            if *new_shares == 0 || *old_shares == 0 {
+                println!("[Corporate Action] Invalid split {}-for-{} on {}", new_shares, old_shares, stock.name);
+                return;
+            }
+            // Nothing changes unless every share count still fits afterwards
+            let availability = split_shares(stock.availability, *new_shares, *old_shares);
+            let Some(availability) = availability.filter(|_| {
+                book.split_fits(*new_shares, *old_shares) && accounts.split_fits(&stock.name, *new_shares, *old_shares)
+            }) else {
+                println!(
+                    "[Corporate Action] Rejected {}-for-{} split on {}: share counts would overflow",
+                    new_shares, old_shares, stock.name
+                );
+                return;
+            };
+            let (close, shares) = (stock.price, stock.availability);
+            stock.price = (stock.price * *old_shares as f64 / *new_shares as f64 * 100.0).round() / 100.0;
+            stock.availability = availability;
+            accounts.apply_split(&stock.name, *new_shares, *old_shares, stock.price);
+
+            for (limit, order) in book.apply_split(*new_shares, *old_shares) {
+                println!(
+                    "[Order Cancelled: {}] Stock: {}, Order: {}, Limit Price: {:.2}, Less than one share after the split",
+                    order.action, stock.name, order.order_id, limit
+                );
+                accounts.release(&stock.name, &order, "Cancelled", "less than one share after the split");
+            }
+            println!(
+                "[Corporate Action] Stock: {}, {}-for-{} {}, Price: {:.2} -> {:.2}, Availability: {} -> {}",
+                stock.name,
+                new_shares,
+                old_shares,
+                if new_shares < old_shares { "Reverse Split" } else { "Split" },
+                close,
+                stock.price,
+                shares,
+                stock.availability
+            );
+        }
+        CorporateActionKind::SymbolChange { new_symbol } => {
+            if books.contains_key(new_symbol) {
+                println!("[Corporate Action] Cannot rename {} to {}: symbol already in use", stock.name, new_symbol);
+                return;
+            }
+            let book = books.remove(&stock.name).unwrap_or_default();
+            books.insert(new_symbol.clone(), book);
+            accounts.rename_symbol(&stock.name, new_symbol);
+            tape.rename_symbol(&stock.name, new_symbol);
+
+            println!("[Corporate Action] Symbol Change: {} -> {}", stock.name, new_symbol);
+            stock.name = new_symbol.clone();
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+// Default location of the brokers' fee schedules
+pub const FEES_PATH: &str = "config/fees.json";
+
+// Per-share rate that applies once a broker's volume for the month reaches `min_volume` shares
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VolumeTier {
+    pub min_volume: u64,
+    pub per_share: f64,
+}
+
+// What a broker charges its clients on each fill
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FeeSchedule {
+    pub per_share: f64,
+    pub per_trade: f64,
+    pub notional_rate: f64,            // Fraction of the fill's value
+    pub volume_tiers: Vec<VolumeTier>, // Replace `per_share` as monthly volume grows
+    pub maker_rebate: f64,             // Per share, credited when the fill added liquidity to the book
+    pub taker_fee: f64,                // Per share, charged when the fill took liquidity
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        Self {
+            per_share: 0.005,
+            per_trade: 1.0,
+            notional_rate: 0.0,
+            volume_tiers: Vec::new(),
+            maker_rebate: 0.002,
+            taker_fee: 0.003,
+        }
+    }
+}
+
+impl FeeSchedule {
+    // Fee on one fill; negative when the maker rebate outweighs the charges.
+    // `liquidity` is "Maker", "Taker" or "Auction", which is neither.
+    pub fn fee(&self, quantity: u32, price: f64, monthly_volume: u64, liquidity: &str) -> f64 {
+        let per_share = self
+            .volume_tiers
+            .iter()
+            .filter(|tier| monthly_volume >= tier.min_volume)
+            .max_by_key(|tier| tier.min_volume)
+            .map(|tier| tier.per_share)
+            .unwrap_or(self.per_share);
+        let liquidity_per_share = match liquidity {
+            "Maker" => -self.maker_rebate,
+            "Taker" => self.taker_fee,
+            _ => 0.0,
+        };
+        let shares = quantity as f64;
+
+        self.per_trade + shares * (per_share + liquidity_per_share) + shares * price * self.notional_rate
+    }
+}
+
+// Fee schedule of every broker; brokers without one of their own use the default
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FeeSchedules {
+    pub default: FeeSchedule,
+    pub brokers: HashMap<u32, FeeSchedule>,
+}
+
+impl FeeSchedules {
+    // Load the schedules from disk, falling back to the defaults
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                println!("[Fees] Invalid fee schedules {}: {}, using defaults", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn for_broker(&self, broker_id: u32) -> &FeeSchedule {
+        self.brokers.get(&broker_id).unwrap_or(&self.default)
+    }
+}
+
+// Shares traded through each broker this calendar month, for the volume tiers
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BrokerVolumes {
+    month: String, // "YYYY-MM"
+    shares: BTreeMap<u32, u64>,
+}
+
+impl BrokerVolumes {
+    // Volumes start again from zero on the first session of a new month
+    pub fn start_day(&mut self, date: &str) {
+        let month = date.get(..7).unwrap_or(date);
+        if self.month != month {
+            self.month = month.to_string();
+            self.shares.clear();
+        }
+    }
+
+    pub fn get(&self, broker_id: u32) -> u64 {
+        self.shares.get(&broker_id).copied().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, broker_id: u32, quantity: u32) {
+        *self.shares.entry(broker_id).or_insert(0) += quantity as u64;
+    }
+}
@@ -0,0 +1,132 @@
+use crate::brokers::Order;
+use crate::fees::{BrokerVolumes, FeeSchedules};
+use crate::market_data::{is_buy, ExecutionReport, Quote};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// How a backtest decides what would have traded
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FillModel {
+    pub slippage_bps: f64,     // Market orders trade this far through the quote
+    pub limit_fill_ratio: f64, // Share of a resting limit order filled each time the market reaches it
+}
+
+impl Default for FillModel {
+    fn default() -> Self {
+        Self { slippage_bps: 5.0, limit_fill_ratio: 1.0 }
+    }
+}
+
+// Stands in for the stock system during a backtest: fills orders against the recorded quotes
+pub struct SimulatedExchange {
+    model: FillModel,
+    fees: FeeSchedules,
+    volumes: BrokerVolumes,
+    working: Vec<Order>, // Resting limit orders; `quantity` is what is still open
+}
+
+impl SimulatedExchange {
+    pub fn new(model: FillModel, fees: FeeSchedules) -> Self {
+        Self { model, fees, volumes: BrokerVolumes::default(), working: Vec::new() }
+    }
+
+    // Accept an order from a broker and fill whatever of it is marketable now
+    pub fn submit(&mut self, order: Order, quotes: &HashMap<String, Quote>) -> Vec<ExecutionReport> {
+        if order.order_type == "Cancel" {
+            return self.cancel(order.order_id, order.client_id).into_iter().collect();
+        }
+        let Some(quote) = quotes.get(&order.stock) else {
+            let reason = format!("no market data for {}", order.stock);
+            return vec![report(&order, "Rejected", order.price, Some(reason))];
+        };
+        if order.quantity == 0 {
+            return vec![report(&order, "Rejected", order.price, Some("zero quantity".to_string()))];
+        }
+
+        let mut reports = vec![report(&order, "Accepted", order.price, None)];
+        let market = quote.price_for(&order.action);
+        match order.order_type.as_str() {
+            "Market" => {
+                let slippage = market * self.model.slippage_bps / 10_000.0;
+                let price = if is_buy(&order.action) { market + slippage } else { (market - slippage).max(0.01) };
+                reports.push(self.fill(&order, order.quantity, price, "Taker"));
+            }
+            _ if marketable(&order, market) => reports.push(self.fill(&order, order.quantity, market, "Taker")),
+            _ => self.working.push(order),
+        }
+        reports
+    }
+
+    // Fill resting limit orders the new quote has reached, at their limit price
+    pub fn on_quote(&mut self, stock: &str, quote: &Quote) -> Vec<ExecutionReport> {
+        let mut reports = Vec::new();
+        let mut working = std::mem::take(&mut self.working);
+        for order in working.iter_mut().filter(|o| o.stock == stock) {
+            if !marketable(order, quote.price_for(&order.action)) {
+                continue;
+            }
+            let quantity = ((order.quantity as f64 * self.model.limit_fill_ratio).ceil() as u32).clamp(1, order.quantity);
+            reports.push(self.fill(order, quantity, order.price, "Maker"));
+            order.quantity -= quantity;
+        }
+        working.retain(|o| o.quantity > 0);
+        self.working = working;
+        reports
+    }
+
+    pub fn cancel(&mut self, order_id: u32, client_id: u32) -> Option<ExecutionReport> {
+        let index = self.working.iter().position(|o| o.order_id == order_id && o.client_id == client_id)?;
+        let order = self.working.remove(index);
+        Some(report(&order, "Cancelled", order.price, Some("cancelled by client".to_string())))
+    }
+
+    // Whatever is still working when the data runs out
+    pub fn expire_all(&mut self) -> Vec<ExecutionReport> {
+        self.working
+            .drain(..)
+            .map(|order| report(&order, "Expired", order.price, Some("end of backtest".to_string())))
+            .collect()
+    }
+
+    fn fill(&mut self, order: &Order, quantity: u32, price: f64, liquidity: &str) -> ExecutionReport {
+        let volume = self.volumes.get(order.broker_id);
+        let fee = self.fees.for_broker(order.broker_id).fee(quantity, price, volume, liquidity);
+        self.volumes.add(order.broker_id, quantity);
+
+        ExecutionReport {
+            quantity,
+            fee,
+            liquidity: Some(liquidity.to_string()),
+            ..report(order, "Filled", price, None)
+        }
+    }
+}
+
+// A limit order trades once the market is at or through its limit
+fn marketable(order: &Order, market: f64) -> bool {
+    if is_buy(&order.action) {
+        market <= order.price
+    } else {
+        market >= order.price
+    }
+}
+
+fn report(order: &Order, status: &str, price: f64, reason: Option<String>) -> ExecutionReport {
+    ExecutionReport {
+        order_id: order.order_id,
+        client_id: order.client_id,
+        stock: order.stock.clone(),
+        action: order.action.clone(),
+        status: status.to_string(),
+        quantity: order.quantity,
+        price,
+        reason,
+        broker_id: order.broker_id,
+        fee: 0.0,
+        liquidity: None,
+        venue: order.venue.clone(),
+        session: order.session.clone(),
+        exchange_id: String::new(),
+    }
+}
@@ -0,0 +1,157 @@
+use crate::stock_data::Stock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+// Default location of the historical data settings
+pub const HISTORICAL_PATH: &str = "config/historical.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct HistoricalConfig {
+    pub mode: String,      // "Off", "Seed" (start from the last recorded close) or "Replay" (prices follow the bars)
+    pub directory: String, // Holds one `<SYMBOL>.csv` per symbol
+    pub step_secs: u64,    // Time between points of the replayed path; each bar is open, high/low, low/high, close
+    pub loop_at_end: bool, // Start a symbol's bars over once they run out, rather than holding the last close
+}
+
+impl Default for HistoricalConfig {
+    fn default() -> Self {
+        Self { mode: "Off".to_string(), directory: "data/history".to_string(), step_secs: 5, loop_at_end: false }
+    }
+}
+
+impl HistoricalConfig {
+    // Load the settings from disk, falling back to the defaults
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                println!("[Historical] Invalid config {}: {}, using defaults", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn is_replay(&self) -> bool {
+        self.mode == "Replay"
+    }
+}
+
+// One daily or intraday OHLCV bar
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bar {
+    pub time: String, // As written in the file, e.g. "2024-03-01" or "2024-03-01 09:30"
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: u64,
+}
+
+impl Bar {
+    // Prices the bar went through: the low before the high on an up bar, the high first on a down bar
+    pub fn path(&self) -> [f64; 4] {
+        if self.close >= self.open {
+            [self.open, self.low, self.high, self.close]
+        } else {
+            [self.open, self.high, self.low, self.close]
+        }
+    }
+}
+
+// Bars from a CSV file with the header `time,open,high,low,close,volume`, oldest first
+pub fn load_bars(path: &Path) -> io::Result<Vec<Bar>> {
+    let mut bars = Vec::new();
+    for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if number == 0 || line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let price = |index: usize| fields.get(index).and_then(|field| field.parse::<f64>().ok()).filter(|p| *p > 0.0);
+        match (fields.first(), price(1), price(2), price(3), price(4)) {
+            (Some(time), Some(open), Some(high), Some(low), Some(close)) => bars.push(Bar {
+                time: time.to_string(),
+                open,
+                high,
+                low,
+                close,
+                volume: fields.get(5).and_then(|field| field.parse().ok()).unwrap_or(0),
+            }),
+            _ => println!("[Historical] Skipping invalid row {} in {}: {}", number + 1, path.display(), line),
+        }
+    }
+    bars.sort_by(|a, b| a.time.cmp(&b.time));
+    Ok(bars)
+}
+
+// Bars for every listed symbol that has a file in the configured directory
+pub fn load_history(config: &HistoricalConfig, stocks: &[Stock]) -> HashMap<String, Vec<Bar>> {
+    let mut history = HashMap::new();
+    for stock in stocks {
+        let path = Path::new(&config.directory).join(format!("{}.csv", stock.name));
+        if !path.exists() {
+            continue;
+        }
+        match load_bars(&path) {
+            Ok(bars) if !bars.is_empty() => {
+                println!(
+                    "[Historical] {}: {} bars from {} to {}",
+                    stock.name,
+                    bars.len(),
+                    bars[0].time,
+                    bars[bars.len() - 1].time
+                );
+                history.insert(stock.name.clone(), bars);
+            }
+            Ok(_) => println!("[Historical] No bars in {}", path.display()),
+            Err(err) => println!("[Historical] Failed to read {}: {}", path.display(), err),
+        }
+    }
+    history
+}
+
+// Opening prices from the data: the last close when seeding, the first open when replaying
+pub fn seed_prices(stocks: &mut [Stock], history: &HashMap<String, Vec<Bar>>, replay: bool) {
+    for stock in stocks.iter_mut() {
+        let Some(bars) = history.get(&stock.name) else { continue };
+        let price = if replay { bars[0].open } else { bars[bars.len() - 1].close };
+        println!("[Historical] {} opens at {:.2} (was {:.2})", stock.name, price, stock.price);
+        stock.price = price;
+    }
+}
+
+// Where each symbol is along its recorded path
+pub struct Replay {
+    history: HashMap<String, Vec<Bar>>,
+    positions: HashMap<String, usize>, // Index into the bars' combined path
+    loop_at_end: bool,
+}
+
+impl Replay {
+    pub fn new(history: HashMap<String, Vec<Bar>>, loop_at_end: bool) -> Self {
+        Self { history, positions: HashMap::new(), loop_at_end }
+    }
+
+    // Next price of every symbol still on its path
+    pub fn step(&mut self) -> Vec<(String, f64)> {
+        let mut prices = Vec::new();
+        for (stock, bars) in &self.history {
+            let position = self.positions.entry(stock.clone()).or_insert(0);
+            let length = bars.len() * 4;
+            if *position >= length {
+                if !self.loop_at_end {
+                    continue;
+                }
+                println!("[Historical] {} reached the end of its data, starting over", stock);
+                *position = 0;
+            }
+            prices.push((stock.clone(), bars[*position / 4].path()[*position % 4]));
+            *position += 1;
+        }
+        prices
+    }
+}
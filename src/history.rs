@@ -0,0 +1,135 @@
+use crate::market_data::{Quote, TradePrint};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Where the trader records the market data its strategies saw, one JSON record per line
+pub const RECORDING_PATH: &str = "history/market_data.jsonl";
+
+// One replayable piece of market data
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind")]
+pub enum MarketRecord {
+    Quote { timestamp: u64, stock: String, last: f64, bid: Option<f64>, ask: Option<f64> },
+    Trade(TradePrint),
+}
+
+impl MarketRecord {
+    pub fn quote(stock: &str, quote: &Quote) -> Self {
+        MarketRecord::Quote {
+            timestamp: now_millis(),
+            stock: stock.to_string(),
+            last: quote.last,
+            bid: quote.bid,
+            ask: quote.ask,
+        }
+    }
+
+    // Milliseconds since the Unix epoch
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            MarketRecord::Quote { timestamp, .. } => *timestamp,
+            MarketRecord::Trade(trade) => trade.timestamp,
+        }
+    }
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// Appends records to the recording; a file that cannot be opened turns recording off
+pub struct Recorder {
+    file: Option<File>,
+}
+
+impl Recorder {
+    pub fn open(path: &str) -> Self {
+        let opened = Path::new(path)
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(path));
+        match opened {
+            Ok(file) => Self { file: Some(file) },
+            Err(err) => {
+                println!("[History] Not recording market data to {}: {}", path, err);
+                Self { file: None }
+            }
+        }
+    }
+
+    pub fn record(&mut self, record: &MarketRecord) {
+        let Some(file) = self.file.as_mut() else { return };
+        let line = serde_json::to_string(record).expect("Failed to serialize market record");
+        if let Err(err) = writeln!(file, "{}", line) {
+            println!("[History] Failed to record market data: {}", err);
+        }
+    }
+}
+
+// A recording made by the trader; lines that do not parse are skipped
+pub fn load_recording(path: &str) -> io::Result<Vec<MarketRecord>> {
+    let mut records = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(err) => println!("[History] Skipping invalid record: {}: {}", err, line),
+        }
+    }
+    records.sort_by_key(MarketRecord::timestamp);
+    Ok(records)
+}
+
+// Market data from a CSV file with the header `timestamp,stock,price,volume,bid,ask`.
+// Every row is a quote at `price`; rows with a volume are also a trade. Bid and ask may be left empty.
+pub fn load_csv(path: &str) -> io::Result<Vec<MarketRecord>> {
+    let mut records = Vec::new();
+    let mut trade_id = 0;
+    for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if number == 0 || line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let optional = |index: usize| fields.get(index).and_then(|field| field.parse::<f64>().ok());
+        let (Some(timestamp), Some(stock), Some(price)) = (
+            fields.first().and_then(|field| field.parse::<u64>().ok()),
+            fields.get(1).filter(|stock| !stock.is_empty()),
+            optional(2),
+        ) else {
+            println!("[History] Skipping invalid row {}: {}", number + 1, line);
+            continue;
+        };
+        let volume = fields.get(3).and_then(|field| field.parse::<u32>().ok()).unwrap_or(0);
+
+        records.push(MarketRecord::Quote {
+            timestamp,
+            stock: stock.to_string(),
+            last: price,
+            bid: optional(4),
+            ask: optional(5),
+        });
+        if volume > 0 {
+            trade_id += 1;
+            records.push(MarketRecord::Trade(TradePrint {
+                trade_id,
+                stock: stock.to_string(),
+                price,
+                quantity: volume,
+                aggressor: "Unknown".to_string(),
+                timestamp,
+            }));
+        }
+    }
+    records.sort_by_key(MarketRecord::timestamp);
+    Ok(records)
+}
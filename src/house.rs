@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+// Default location of the house liquidity settings
+pub const HOUSE_LIQUIDITY_PATH: &str = "config/house_liquidity.json";
+
+// The house trades whatever the book cannot fill at the current price and moves it against the order.
+// With market makers quoting, it can be turned off so prices and spreads come from the book alone.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct HouseLiquidity {
+    pub enabled: bool,
+    pub impact: f64, // Price move per house fill, as a fraction of the price
+}
+
+impl Default for HouseLiquidity {
+    fn default() -> Self {
+        Self { enabled: true, impact: 0.05 }
+    }
+}
+
+impl HouseLiquidity {
+    // Load the settings from disk, falling back to the defaults
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                println!("[House Liquidity] Invalid config {}: {}, using defaults", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Every order the stock system has taken, one JSON line each, so an order redelivered after a crash or a
+// lost connection is recognised instead of traded again
+pub const ORDER_JOURNAL_PATH: &str = "state/order_journal.jsonl";
+
+// How long a message can wait to be delivered again; older journal entries are dropped when it opens
+pub const REDELIVERY_WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+
+// Identifies one trader process: its start time and process id, so traders started at the same
+// moment on one machine still differ. Every order id the trader sends is only unique within it.
+pub fn new_session_id() -> String {
+    format!("{}-{}", base36(millis_since_epoch()), base36(std::process::id() as u64))
+}
+
+// The globally unique name of a client's order: the sending trader's session and its order id
+pub fn client_order_id(session: &str, order_id: u32) -> String {
+    if session.is_empty() {
+        order_id.to_string()
+    } else {
+        format!("{}-{}", session, order_id)
+    }
+}
+
+// Queue a trader session's execution reports are published to; orders without a session report on the shared queue
+pub fn execution_queue(session: &str) -> String {
+    if session.is_empty() {
+        "execution_reports".to_string()
+    } else {
+        format!("execution_reports.{}", session)
+    }
+}
+
+// One order's line in the journal
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    pub client_order_id: String,
+    pub exchange_id: String,
+    pub status: String, // "Pending" when taken, before it is applied, then "Accepted" or "Rejected"
+    pub reason: Option<String>,
+    pub timestamp: u64, // Milliseconds since the Unix epoch
+}
+
+// The stock system's side of order identity: the client order ids it has already taken, and the
+// exchange ids it assigns to them
+pub struct OrderIds {
+    prefix: String, // Venue and process start, so ids from a restarted stock system do not repeat
+    next: u64,
+    seen: HashMap<String, Option<String>>, // Each order taken, with the reason if it was rejected
+    journal: String,
+    unwritten: HashMap<String, JournalEntry>, // Taken, but writing them to the journal failed
+}
+
+impl OrderIds {
+    // Carries on from the journal at `journal`, creating it on the first order. Orders taken before the
+    // redelivery window cannot come back, so they are left out and the journal rewritten without them.
+    pub fn open(venue: &str, journal: &str) -> Self {
+        let cutoff = millis_since_epoch().saturating_sub(REDELIVERY_WINDOW_MS);
+        let (kept, expired): (Vec<JournalEntry>, Vec<JournalEntry>) = fs::read_to_string(journal)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str::<JournalEntry>(line).ok())
+            .partition(|entry| entry.timestamp >= cutoff);
+        if !expired.is_empty() {
+            match compact(journal, &kept) {
+                Ok(()) => println!("[Order Journal] Dropped {} orders older than the redelivery window", expired.len()),
+                Err(err) => println!("[Order Journal] Failed to compact {}: {}", journal, err),
+            }
+        }
+        // An order's later lines override its earlier ones; one still pending was taken before a crash
+        let latest: HashMap<String, JournalEntry> =
+            kept.into_iter().map(|entry| (entry.client_order_id.clone(), entry)).collect();
+        let pending = latest.values().filter(|entry| entry.status == "Pending").count();
+        if pending > 0 {
+            println!("[Order Journal] {} orders were taken without their outcome recorded; they are not traded again", pending);
+        }
+        let seen: HashMap<String, Option<String>> = latest.into_iter().map(|(id, entry)| (id, entry.reason)).collect();
+        if !seen.is_empty() {
+            println!("[Order Journal] {} orders already taken, from {}", seen.len(), journal);
+        }
+        Self {
+            prefix: format!("{}-{}", venue, base36(millis_since_epoch())),
+            next: 0,
+            seen,
+            journal: journal.to_string(),
+            unwritten: HashMap::new(),
+        }
+    }
+
+    pub fn contains(&self, client_order_id: &str) -> bool {
+        self.seen.contains_key(client_order_id)
+    }
+
+    // An exchange id for a new order, or None if its client order id has been used before
+    pub fn assign(&mut self, client_order_id: &str) -> Option<String> {
+        if self.seen.contains_key(client_order_id) {
+            return None;
+        }
+        self.seen.insert(client_order_id.to_string(), None);
+        self.next += 1;
+        Some(format!("{}-{}", self.prefix, self.next))
+    }
+
+    // Append an order taken with `assign` before it is applied, synced to disk before returning, so an
+    // order redelivered after a crash is known to be taken even if its outcome never made it to the
+    // journal. On failure the order is forgotten again, to be taken when it is redelivered.
+    pub fn record_intent(&mut self, client_order_id: &str, exchange_id: &str) -> io::Result<()> {
+        let entry = JournalEntry {
+            client_order_id: client_order_id.to_string(),
+            exchange_id: exchange_id.to_string(),
+            status: "Pending".to_string(),
+            reason: None,
+            timestamp: millis_since_epoch(),
+        };
+        self.append(&entry).inspect_err(|_| {
+            self.seen.remove(client_order_id);
+        })
+    }
+
+    // Append what became of an order once it has been applied, synced to disk before returning. On failure
+    // the entry is kept and written by the next `ensure_recorded`.
+    pub fn record(&mut self, client_order_id: &str, exchange_id: &str, reason: Option<String>) -> io::Result<()> {
+        self.seen.insert(client_order_id.to_string(), reason.clone());
+        let entry = JournalEntry {
+            client_order_id: client_order_id.to_string(),
+            exchange_id: exchange_id.to_string(),
+            status: if reason.is_some() { "Rejected" } else { "Accepted" }.to_string(),
+            reason,
+            timestamp: millis_since_epoch(),
+        };
+        self.append(&entry).inspect_err(|_| {
+            self.unwritten.insert(entry.client_order_id.clone(), entry.clone());
+        })
+    }
+
+    // An order already taken is in the journal, retrying the write if it failed the first time. Gives
+    // back why the order was rejected, if it was.
+    pub fn ensure_recorded(&mut self, client_order_id: &str) -> io::Result<Option<String>> {
+        if let Some(entry) = self.unwritten.get(client_order_id).cloned() {
+            self.append(&entry)?;
+            self.unwritten.remove(client_order_id);
+        }
+        Ok(self.seen.get(client_order_id).cloned().flatten())
+    }
+
+    fn append(&self, entry: &JournalEntry) -> io::Result<()> {
+        if let Some(directory) = Path::new(&self.journal).parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.journal)?;
+        let line = serde_json::to_string(entry).map_err(io::Error::other)?;
+        writeln!(file, "{}", line)?;
+        file.sync_data()
+    }
+}
+
+// Replace the journal with `entries`, through a temporary file so a crash never loses the orders kept
+fn compact(journal: &str, entries: &[JournalEntry]) -> io::Result<()> {
+    let temp = format!("{}.tmp", journal);
+    let mut contents = String::new();
+    for entry in entries {
+        contents.push_str(&serde_json::to_string(entry).map_err(io::Error::other)?);
+        contents.push('\n');
+    }
+    fs::write(&temp, contents)?;
+    fs::rename(&temp, journal)
+}
+
+fn millis_since_epoch() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn base36(mut value: u64) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut digits = Vec::new();
+    loop {
+        digits.push(DIGITS[(value % 36) as usize]);
+        value /= 36;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).expect("base36 digits are ASCII")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh journal path under the temp directory for one test
+    fn journal(name: &str) -> String {
+        let directory = std::env::temp_dir().join(format!("rts-order-journal-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&directory);
+        directory.join("order_journal.jsonl").to_string_lossy().into_owned()
+    }
+
+    fn write_journal(path: &str, entries: &[JournalEntry]) {
+        fs::create_dir_all(Path::new(path).parent().unwrap()).unwrap();
+        compact(path, entries).unwrap();
+    }
+
+    #[test]
+    fn client_order_ids_are_scoped_to_the_session() {
+        assert_eq!(client_order_id("", 7), "7");
+        assert_eq!(client_order_id("abc-12", 7), "abc-12-7");
+        assert_eq!(execution_queue(""), "execution_reports");
+        assert_eq!(execution_queue("abc-12"), "execution_reports.abc-12");
+    }
+
+    #[test]
+    fn each_client_order_id_is_taken_once() {
+        let mut ids = OrderIds::open("XNYS", &journal("taken-once"));
+
+        let first = ids.assign("abc-1").expect("new order");
+        let second = ids.assign("def-1").expect("same order id from another session");
+
+        assert!(first.starts_with("XNYS-"));
+        assert_ne!(first, second);
+        assert_eq!(ids.assign("abc-1"), None);
+    }
+
+    #[test]
+    fn journaled_orders_are_recognised_after_a_restart() {
+        let path = journal("restart");
+        let mut ids = OrderIds::open("XNYS", &path);
+        let accepted = ids.assign("abc-1").unwrap();
+        ids.record_intent("abc-1", &accepted).unwrap();
+        ids.record("abc-1", &accepted, None).unwrap();
+        let rejected = ids.assign("abc-2").unwrap();
+        ids.record_intent("abc-2", &rejected).unwrap();
+        ids.record("abc-2", &rejected, Some("Unknown stock".to_string())).unwrap();
+
+        let mut reopened = OrderIds::open("XNYS", &path);
+
+        assert!(reopened.contains("abc-1") && reopened.contains("abc-2"));
+        assert_eq!(reopened.assign("abc-1"), None);
+        assert!(reopened.assign("abc-3").is_some());
+        // A redelivered order is answered as it was the first time
+        assert_eq!(reopened.ensure_recorded("abc-1").unwrap(), None);
+        assert_eq!(reopened.ensure_recorded("abc-2").unwrap(), Some("Unknown stock".to_string()));
+    }
+
+    #[test]
+    fn an_order_taken_before_a_crash_is_not_taken_again() {
+        let path = journal("crash");
+        let mut ids = OrderIds::open("XNYS", &path);
+        let exchange_id = ids.assign("abc-1").unwrap();
+        ids.record_intent("abc-1", &exchange_id).unwrap();
+        // The process dies here, after taking the order but before recording its outcome
+
+        let mut reopened = OrderIds::open("XNYS", &path);
+
+        assert!(reopened.contains("abc-1"));
+        assert_eq!(reopened.ensure_recorded("abc-1").unwrap(), None);
+        assert_eq!(reopened.assign("abc-1"), None);
+    }
+
+    #[test]
+    fn an_order_whose_intent_was_not_written_can_be_taken_again() {
+        let path = journal("intent");
+        let directory = Path::new(&path).parent().unwrap().to_path_buf();
+        fs::write(&directory, "").unwrap();
+        let mut ids = OrderIds::open("XNYS", &path);
+        let exchange_id = ids.assign("abc-1").unwrap();
+
+        assert!(ids.record_intent("abc-1", &exchange_id).is_err());
+        assert!(!ids.contains("abc-1"));
+
+        fs::remove_file(&directory).unwrap();
+        let exchange_id = ids.assign("abc-1").expect("taken when redelivered");
+        ids.record_intent("abc-1", &exchange_id).unwrap();
+    }
+
+    #[test]
+    fn a_failed_write_is_retried_by_ensure_recorded() {
+        let path = journal("retry");
+        let directory = Path::new(&path).parent().unwrap().to_path_buf();
+        // A file where the journal's directory should be makes the first write fail
+        fs::write(&directory, "").unwrap();
+        let mut ids = OrderIds::open("XNYS", &path);
+        let exchange_id = ids.assign("abc-1").unwrap();
+        assert!(ids.record("abc-1", &exchange_id, None).is_err());
+        assert!(ids.ensure_recorded("abc-1").is_err());
+
+        fs::remove_file(&directory).unwrap();
+        ids.ensure_recorded("abc-1").unwrap();
+
+        assert!(OrderIds::open("XNYS", &path).contains("abc-1"));
+    }
+
+    #[test]
+    fn entries_older_than_the_redelivery_window_are_dropped() {
+        let path = journal("prune");
+        let entry = |id: &str, timestamp: u64| JournalEntry {
+            client_order_id: id.to_string(),
+            exchange_id: format!("XNYS-old-{}", id),
+            status: "Accepted".to_string(),
+            reason: None,
+            timestamp,
+        };
+        let now = millis_since_epoch();
+        write_journal(&path, &[entry("abc-1", now - REDELIVERY_WINDOW_MS - 1), entry("abc-2", now)]);
+
+        let ids = OrderIds::open("XNYS", &path);
+
+        assert!(!ids.contains("abc-1"));
+        assert!(ids.contains("abc-2"));
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+    }
+}
@@ -0,0 +1,32 @@
+// Everything the stock system, the trader and the backtest share
+pub mod accounts;
+pub mod agents;
+pub mod algos;
+pub mod amqp;
+pub mod auction;
+pub mod blotter;
+pub mod brokers;
+pub mod clearing;
+pub mod corporate_actions;
+pub mod fees;
+pub mod fill_model;
+pub mod historical;
+pub mod history;
+pub mod house;
+pub mod ids;
+pub mod margin;
+pub mod market_data;
+pub mod market_state;
+pub mod order_book;
+pub mod order_groups;
+pub mod performance;
+pub mod portfolio;
+pub mod routing;
+pub mod session;
+pub mod shutdown;
+pub mod smart_router;
+pub mod stock_data;
+pub mod strategy;
+pub mod supervisor;
+pub mod trade_tape;
+pub mod venues;
@@ -0,0 +1,204 @@
+use crate::brokers::Order;
+use crate::market_data::{ExecutionReport, Quote};
+use crate::portfolio::ClientPosition;
+use crate::strategy::OrderRequest;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Default location of the margin terms
+pub const MARGIN_PATH: &str = "config/margin.json";
+
+// Fractions of a position's market value the client must cover with equity
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct MarginRates {
+    pub initial: f64,     // To open or add to a position
+    pub maintenance: f64, // To keep it open
+}
+
+// Margin terms of the clients' accounts, with overrides for riskier symbols
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MarginConfig {
+    pub starting_cash: f64,
+    pub default_rates: MarginRates,
+    pub symbols: HashMap<String, MarginRates>,
+    pub call_grace_secs: u64, // Time to meet a margin call before positions are liquidated
+}
+
+impl Default for MarginConfig {
+    fn default() -> Self {
+        Self {
+            starting_cash: 100_000.0,
+            default_rates: MarginRates { initial: 0.5, maintenance: 0.25 },
+            symbols: HashMap::new(),
+            call_grace_secs: 15,
+        }
+    }
+}
+
+impl MarginConfig {
+    // Load the terms from disk, falling back to the defaults
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                println!("[Margin] Invalid config {}: {}, using defaults", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn rates(&self, stock: &str) -> MarginRates {
+        self.symbols.get(stock).copied().unwrap_or(self.default_rates)
+    }
+
+    pub fn call_grace(&self) -> Duration {
+        Duration::from_secs(self.call_grace_secs)
+    }
+}
+
+// Margin position of one client, marked to the latest prices
+#[derive(Debug, Clone)]
+pub struct MarginStatus {
+    pub equity: f64,
+    pub initial_requirement: f64,
+    pub maintenance_requirement: f64,
+}
+
+impl MarginStatus {
+    // Value the client may add in `stock` before hitting the initial requirement
+    pub fn buying_power(&self, rates: MarginRates) -> f64 {
+        ((self.equity - self.initial_requirement) / rates.initial).max(0.0)
+    }
+
+    // Equity missing to meet the maintenance requirement
+    pub fn deficit(&self) -> f64 {
+        (self.maintenance_requirement - self.equity).max(0.0)
+    }
+}
+
+// Equity and requirements of a client's positions at the marks in `stock_prices`
+pub fn status(client: &ClientPosition, stock_prices: &HashMap<String, Quote>, config: &MarginConfig) -> MarginStatus {
+    let mut status = MarginStatus { equity: client.cash, initial_requirement: 0.0, maintenance_requirement: 0.0 };
+    for (stock, position) in client.positions.iter() {
+        let Some(quote) = stock_prices.get(stock) else { continue };
+        let rates = config.rates(stock);
+        let value = *position as f64 * quote.last;
+        status.equity += value;
+        status.initial_requirement += value.abs() * rates.initial;
+        status.maintenance_requirement += value.abs() * rates.maintenance;
+    }
+    status
+}
+
+// A strategy's request as an order with a unique id, capped at what the client's buying power allows.
+// An order that cannot be sent at all comes back as the rejection its strategy should see, since the
+// strategy already counts it as in flight.
+pub fn new_order(
+    client_id: u32,
+    request: OrderRequest,
+    order_ids: &Mutex<u32>,
+    client: &ClientPosition,
+    stock_prices: &HashMap<String, Quote>,
+    config: &MarginConfig,
+) -> Result<Order, Box<ExecutionReport>> {
+    let order_id = {
+        let mut id = order_ids.lock().unwrap();
+        *id += 1;
+        *id
+    };
+    let mut quantity = request.quantity;
+
+    // Orders that open or add to a position need initial margin
+    if request.action == "Buy" || request.action == "SellShort" {
+        let status = status(client, stock_prices, config);
+        let affordable = (status.buying_power(config.rates(&request.stock)) / request.price.max(0.01)) as u32;
+        if affordable == 0 {
+            let reason = format!(
+                "no buying power: equity {:.2}, initial requirement {:.2}",
+                status.equity, status.initial_requirement
+            );
+            return Err(Box::new(ExecutionReport {
+                order_id,
+                client_id,
+                stock: request.stock,
+                action: request.action,
+                status: "Rejected".to_string(),
+                quantity: request.quantity,
+                price: request.price,
+                reason: Some(reason),
+                broker_id: 0,
+                fee: 0.0,
+                liquidity: None,
+                venue: String::new(),
+                session: String::new(),
+                exchange_id: String::new(),
+            }));
+        }
+        quantity = quantity.min(affordable);
+    }
+
+    Ok(Order {
+        order_id,
+        client_id,
+        broker_id: 0,           // Set by the broker that handles it
+        session: String::new(), // Set by the broker too
+        stock: request.stock,
+        action: request.action,
+        quantity,
+        price: request.price,
+        order_type: request.order_type,
+        time_in_force: request.time_in_force,
+        venue: String::new(), // Chosen by the broker
+        algo: request.algo,
+        links: request.links,
+    })
+}
+
+// Margin call issued to a client whose equity fell below the maintenance requirement
+#[derive(Debug, Clone)]
+pub struct MarginCall {
+    pub client_id: u32,
+    pub equity: f64,
+    pub maintenance_requirement: f64,
+    pub deficit: f64,
+    pub issued_at: Instant,
+}
+
+// Closing trades (stock, action, quantity) that bring the maintenance requirement back within the
+// client's equity, taking the positions with the largest requirement first
+pub fn liquidation_orders(
+    client: &ClientPosition,
+    stock_prices: &HashMap<String, Quote>,
+    config: &MarginConfig,
+) -> Vec<(String, &'static str, u32)> {
+    let current = status(client, stock_prices, config);
+    let mut excess = current.maintenance_requirement - current.equity;
+
+    let mut positions: Vec<(String, i64, f64)> = client
+        .positions
+        .iter()
+        .filter_map(|(stock, position)| {
+            let quote = stock_prices.get(stock)?;
+            let per_share = quote.last * config.rates(stock).maintenance;
+            Some((stock.clone(), *position, per_share))
+        })
+        .collect();
+    positions.sort_by(|a, b| (b.1.abs() as f64 * b.2).total_cmp(&(a.1.abs() as f64 * a.2)));
+
+    let mut orders = Vec::new();
+    for (stock, position, per_share) in positions {
+        if excess <= 0.0 || per_share <= 0.0 {
+            break;
+        }
+        let held = position.unsigned_abs();
+        let quantity = ((excess / per_share).ceil() as u64).min(held) as u32;
+        excess -= quantity as f64 * per_share;
+        let action = if position > 0 { "Sell" } else { "BuyToCover" };
+        orders.push((stock, action, quantity));
+    }
+    orders
+}
@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Number of price levels per side carried on the depth feed
+pub const DEPTH_LEVELS: usize = 5;
+
+// Aggregated quantity resting at one price level
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DepthLevel {
+    pub price: f64,
+    pub quantity: u32,
+}
+
+// Messages published on the "market_depth" routing key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum DepthMessage {
+    // Full top-N picture of a book, carrying the sequence number of the last update it includes
+    Snapshot {
+        stock: String,
+        seq: u64,
+        bids: Vec<DepthLevel>,
+        asks: Vec<DepthLevel>,
+    },
+    // A single level changed; quantity 0 removes the level
+    Update {
+        stock: String,
+        seq: u64,
+        side: String, // "Buy" or "Sell"
+        price: f64,
+        quantity: u32,
+    },
+    // Best bid/offer, sent whenever the top of the book changes
+    TopOfBook {
+        stock: String,
+        seq: u64,
+        bid: Option<DepthLevel>,
+        ask: Option<DepthLevel>,
+    },
+}
+
+// Trader-side view of a symbol: last traded price plus best bid/ask
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub last: f64,
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
+}
+
+impl Quote {
+    pub fn new(last: f64) -> Self {
+        Self { last, bid: None, ask: None }
+    }
+
+    // Price a market order with the given action is expected to trade at
+    pub fn price_for(&self, action: &str) -> f64 {
+        if is_buy(action) {
+            self.ask.unwrap_or(self.last)
+        } else {
+            self.bid.unwrap_or(self.last)
+        }
+    }
+}
+
+// Local copy of a symbol's depth, rebuilt from snapshots and incremental updates
+#[derive(Debug, Default)]
+pub struct DepthBook {
+    pub seq: u64,
+    pub stale: bool,
+    pub bids: BTreeMap<u64, u32>,
+    pub asks: BTreeMap<u64, u32>,
+}
+
+impl DepthBook {
+    // Apply a feed message, returning false if a sequence gap left the book stale
+    pub fn apply(&mut self, message: &DepthMessage) -> bool {
+        match message {
+            DepthMessage::Snapshot { seq, bids, asks, .. } => {
+                if *seq < self.seq && !self.stale {
+                    return true;
+                }
+                self.bids = bids.iter().map(|l| (to_ticks(l.price), l.quantity)).collect();
+                self.asks = asks.iter().map(|l| (to_ticks(l.price), l.quantity)).collect();
+                self.seq = *seq;
+                self.stale = false;
+            }
+            DepthMessage::Update { seq, side, price, quantity, .. } => {
+                if self.stale || *seq <= self.seq {
+                    return !self.stale;
+                }
+                if *seq != self.seq + 1 {
+                    // Missed an update; wait for the next snapshot
+                    self.stale = true;
+                    return false;
+                }
+                let levels = if side == "Buy" { &mut self.bids } else { &mut self.asks };
+                if *quantity == 0 {
+                    levels.remove(&to_ticks(*price));
+                } else {
+                    levels.insert(to_ticks(*price), *quantity);
+                }
+                self.seq = *seq;
+            }
+            DepthMessage::TopOfBook { .. } => {}
+        }
+        !self.stale
+    }
+
+    // Best bid and offer with the quantity shown at each
+    pub fn top(&self) -> (Option<DepthLevel>, Option<DepthLevel>) {
+        let level = |(ticks, quantity): (&u64, &u32)| DepthLevel { price: from_ticks(*ticks), quantity: *quantity };
+        (self.bids.iter().next_back().map(level), self.asks.iter().next().map(level))
+    }
+}
+
+// Side of the book an order action trades on: "Buy" and "BuyToCover" buy, "Sell" and "SellShort" sell
+pub fn side(action: &str) -> &'static str {
+    match action {
+        "Buy" | "BuyToCover" => "Buy",
+        _ => "Sell",
+    }
+}
+
+pub fn is_buy(action: &str) -> bool {
+    side(action) == "Buy"
+}
+
+// Prices are keyed in whole cents so they can be ordered and compared exactly
+pub fn to_ticks(price: f64) -> u64 {
+    (price * 100.0).round().max(0.0) as u64
+}
+
+pub fn from_ticks(ticks: u64) -> f64 {
+    ticks as f64 / 100.0
+}
+
+// Time-and-sales print for a single fill, published on the "trade_tape" routing key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TradePrint {
+    pub trade_id: u64,
+    pub stock: String,
+    pub price: f64,
+    pub quantity: u32,
+    pub aggressor: String, // "Buy", "Sell", or "Auction" for a call auction cross
+    pub timestamp: u64,    // Milliseconds since the Unix epoch
+}
+
+// Request for the most recent trades in a symbol, sent on the "trade_tape_query" routing key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TradeQuery {
+    pub stock: String,
+    pub count: usize,
+}
+
+// Close-of-day summary for one symbol, published on the "market_summary" routing key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DaySummary {
+    pub stock: String,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: u64,
+    pub trades: u64,
+}
+
+// Indicative or final result of a call auction, published on the "auction_updates" routing key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuctionUpdate {
+    pub stock: String,
+    pub auction: String,               // "Opening" or "Closing"
+    pub indicative_price: Option<f64>, // None while buy and sell interest do not cross
+    pub matched_volume: u64,
+    pub imbalance: i64,                // Buy minus sell quantity; positive means excess demand
+    pub uncrossed: bool,               // True for the final result at the end of the auction
+}
+
+// Adjustment a corporate action makes on its ex-date
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum CorporateActionKind {
+    CashDividend { amount: f64 },               // Per share, paid to holders of record
+    Split { new_shares: u32, old_shares: u32 }, // A reverse split when new_shares < old_shares
+    SymbolChange { new_symbol: String },
+}
+
+// Shares after a `new_shares`-for-`old_shares` split, or None if they no longer fit in a u32
+pub fn split_shares(shares: u32, new_shares: u32, old_shares: u32) -> Option<u32> {
+    u32::try_from(shares as u64 * new_shares as u64 / old_shares as u64).ok()
+}
+
+// A scheduled corporate action
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CorporateAction {
+    pub stock: String,
+    pub ex_date: String, // "YYYY-MM-DD"
+    #[serde(flatten)]
+    pub kind: CorporateActionKind,
+}
+
+// Corporate action announcement, published on the "corporate_actions" routing key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CorporateActionNotice {
+    pub status: String, // "Announced" the session before the ex-date, "Effective" on it
+    #[serde(flatten)]
+    pub action: CorporateAction,
+}
+
+// Outcome of an order for its client, published on the "execution_reports" routing key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExecutionReport {
+    pub order_id: u32,
+    pub client_id: u32,
+    pub stock: String,
+    pub action: String,         // "Buy", "Sell" (long sale), "SellShort" or "BuyToCover"
+    pub status: String,         // "Accepted", "Rejected", "Filled", "Expired" or "Cancelled"
+    pub quantity: u32,          // Shares accepted or filled, or left unexecuted when the order ends
+    pub price: f64,             // Fill price, or the limit price when accepted
+    pub reason: Option<String>, // Why the order was rejected, cancelled or expired
+    #[serde(default)]
+    pub broker_id: u32,
+    #[serde(default)]
+    pub fee: f64, // Charged by the broker on a fill; negative for a net rebate
+    #[serde(default)]
+    pub liquidity: Option<String>, // "Maker", "Taker" or "Auction" on a fill
+    #[serde(default)]
+    pub venue: String, // Venue the order traded on
+    #[serde(default)]
+    pub session: String, // Trader session that sent the order; with `order_id` it names the order
+    #[serde(default)]
+    pub exchange_id: String, // The stock system's id for the order; empty for orders a broker manages itself
+}
+
+// Status of a broker's settlement obligation in one symbol, published on the "settlement_reports" routing key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SettlementReport {
+    pub broker_id: u32,
+    pub stock: String,
+    pub trade_date: String,
+    pub settlement_date: String,
+    pub net_shares: i64, // Received (+) or delivered (-) by the broker's clients
+    pub net_cash: f64,   // Received (+) or paid (-) by the broker's clients
+    pub status: String,  // "Pending" once netted, then "Settled" or "Failed"
+    pub failed_shares: u64,
+}
+
+// Market-wide event that moved prices, published on the "market_news" routing key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MarketNews {
+    pub event_name: String,
+    pub impact: f64, // Fraction every price moved by
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(seq: u64, bids: &[(f64, u32)], asks: &[(f64, u32)]) -> DepthMessage {
+        let levels = |levels: &[(f64, u32)]| {
+            levels.iter().map(|&(price, quantity)| DepthLevel { price, quantity }).collect()
+        };
+        DepthMessage::Snapshot { stock: "AAPL".to_string(), seq, bids: levels(bids), asks: levels(asks) }
+    }
+
+    fn update(seq: u64, side: &str, price: f64, quantity: u32) -> DepthMessage {
+        DepthMessage::Update { stock: "AAPL".to_string(), seq, side: side.to_string(), price, quantity }
+    }
+
+    fn level(price: f64, quantity: u32) -> Option<DepthLevel> {
+        Some(DepthLevel { price, quantity })
+    }
+
+    #[test]
+    fn updates_apply_in_sequence() {
+        let mut book = DepthBook::default();
+        assert!(book.apply(&snapshot(5, &[(10.00, 100)], &[(10.05, 200)])));
+        assert!(book.apply(&update(6, "Buy", 10.01, 50)));
+        assert!(book.apply(&update(7, "Sell", 10.05, 0)));
+
+        assert_eq!(book.seq, 7);
+        assert_eq!(book.top(), (level(10.01, 50), None));
+    }
+
+    #[test]
+    fn old_and_repeated_messages_are_ignored() {
+        let mut book = DepthBook::default();
+        book.apply(&snapshot(5, &[(10.00, 100)], &[]));
+        book.apply(&update(6, "Buy", 10.00, 150));
+
+        assert!(book.apply(&update(6, "Buy", 10.00, 300)));
+        assert!(book.apply(&update(4, "Buy", 10.00, 0)));
+        assert!(book.apply(&snapshot(3, &[(9.00, 1)], &[])));
+
+        assert_eq!(book.seq, 6);
+        assert_eq!(book.top(), (level(10.00, 150), None));
+    }
+
+    #[test]
+    fn gap_leaves_the_book_stale_until_the_next_snapshot() {
+        let mut book = DepthBook::default();
+        book.apply(&snapshot(5, &[(10.00, 100)], &[]));
+
+        assert!(!book.apply(&update(7, "Buy", 10.01, 50)));
+        assert!(book.stale);
+        assert!(!book.apply(&update(8, "Buy", 10.02, 50)));
+        assert_eq!(book.top(), (level(10.00, 100), None));
+
+        // Any snapshot resyncs a stale book, even one older than the last update seen
+        assert!(book.apply(&snapshot(4, &[(10.01, 50)], &[(10.03, 10)])));
+        assert!(!book.stale);
+        assert!(book.apply(&update(5, "Sell", 10.02, 20)));
+        assert_eq!(book.top(), (level(10.01, 50), level(10.02, 20)));
+    }
+}
@@ -0,0 +1,60 @@
+use crate::accounts::Accounts;
+use crate::order_book::IncomingOrder;
+use crate::stock_data::Stock;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Where the end-of-day state is kept between sessions in multi-day mode
+pub const STATE_PATH: &str = "state/market_state.json";
+
+// Everything one session's close hands over to the next session's open
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EndOfDayState {
+    pub day: u32,
+    pub date: String,
+    pub stocks: Vec<Stock>,
+    pub gtc_orders: Vec<IncomingOrder>,
+    pub accounts: Accounts,
+}
+
+impl EndOfDayState {
+    // The last saved state, if there is one
+    pub fn load(path: &str) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&contents) {
+            Ok(state) => Some(state),
+            Err(err) => {
+                println!("[Market State] Ignoring invalid state in {}: {}", path, err);
+                None
+            }
+        }
+    }
+
+    // Write to a temporary file first so a crash mid-save never leaves a truncated state behind
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp = format!("{}.tmp", path);
+        let contents = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(&temp, contents)?;
+        fs::rename(&temp, path)
+    }
+}
+
+// Move every price by a random overnight gap of up to +/- `max_gap`
+pub fn apply_overnight_gap(stocks: &mut [Stock], max_gap: f64) {
+    let mut rng = rand::thread_rng();
+    for stock in stocks.iter_mut() {
+        let gap = if max_gap > 0.0 { rng.gen_range(-max_gap..=max_gap) } else { 0.0 };
+        let close = stock.price;
+        stock.price = (close + close * gap).max(1.0);
+        println!(
+            "[Overnight Gap] Stock: {}, Close: {:.2}, Open Reference: {:.2} ({:+.2}%)",
+            stock.name, close, stock.price, gap * 100.0
+        );
+    }
+}
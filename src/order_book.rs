@@ -0,0 +1,443 @@
+use crate::ids::client_order_id;
+use crate::market_data::{from_ticks, is_buy, split_shares, to_ticks, DepthLevel, DepthMessage, DEPTH_LEVELS};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+// An order as received by the stock system
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IncomingOrder {
+    pub order_id: u32, // The client's id, unique within its trader session
+    pub client_id: u32,
+    #[serde(default)]
+    pub broker_id: u32,
+    #[serde(default)]
+    pub session: String, // Trader session that sent the order
+    #[serde(default)]
+    pub exchange_id: String, // Assigned by the stock system once it takes the order
+    pub stock: String,
+    pub action: String,        // "Buy", "Sell" (long sale), "SellShort" or "BuyToCover"
+    pub quantity: u32,
+    pub order_type: String,    // "Market" or "Limit"
+    pub price: f64,            // Limit price; ignored for market orders
+    pub time_in_force: String, // "Day" or "GTC"
+}
+
+impl IncomingOrder {
+    pub fn client_order_id(&self) -> String {
+        client_order_id(&self.session, self.order_id)
+    }
+
+    // `quantity` shares of this order, e.g. the part that rests in the book or a single fill
+    pub fn resting(&self, quantity: u32) -> RestingOrder {
+        RestingOrder {
+            order_id: self.order_id,
+            client_id: self.client_id,
+            broker_id: self.broker_id,
+            session: self.session.clone(),
+            exchange_id: self.exchange_id.clone(),
+            action: self.action.clone(),
+            quantity,
+            time_in_force: self.time_in_force.clone(),
+        }
+    }
+}
+
+// A limit order waiting in the book
+#[derive(Debug, Clone)]
+pub struct RestingOrder {
+    pub order_id: u32,
+    pub client_id: u32,
+    pub broker_id: u32,
+    pub session: String,
+    pub exchange_id: String,
+    pub action: String,
+    pub quantity: u32,
+    pub time_in_force: String,
+}
+
+impl RestingOrder {
+    // Back to a full limit order, e.g. to enter an auction or be saved overnight
+    pub fn into_incoming(self, stock: &str, price: f64) -> IncomingOrder {
+        IncomingOrder {
+            order_id: self.order_id,
+            client_id: self.client_id,
+            broker_id: self.broker_id,
+            session: self.session,
+            exchange_id: self.exchange_id,
+            stock: stock.to_string(),
+            action: self.action,
+            quantity: self.quantity,
+            order_type: "Limit".to_string(),
+            price,
+            time_in_force: self.time_in_force,
+        }
+    }
+
+    pub fn is_gtc(&self) -> bool {
+        self.time_in_force == "GTC"
+    }
+}
+
+// Result of an incoming order trading against a resting one
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub resting: RestingOrder, // The resting order's side of the trade, for the quantity filled
+    pub price: f64,
+}
+
+// Price-time priority book for a single symbol
+#[derive(Debug, Default, Clone)]
+pub struct OrderBook {
+    bids: BTreeMap<u64, VecDeque<RestingOrder>>,
+    asks: BTreeMap<u64, VecDeque<RestingOrder>>,
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Rest a limit order at the back of its price level
+    pub fn add_limit(&mut self, price: f64, order: RestingOrder) {
+        let side = if is_buy(&order.action) { &mut self.bids } else { &mut self.asks };
+        side.entry(to_ticks(price)).or_default().push_back(order);
+    }
+
+    // Match an incoming order against the opposite side, up to an optional limit price
+    pub fn match_order(&mut self, action: &str, quantity: u32, limit: Option<f64>) -> Vec<Fill> {
+        let buy = is_buy(action);
+        let mut fills = Vec::new();
+        let mut remaining = quantity;
+        let limit_ticks = limit.map(to_ticks);
+
+        while remaining > 0 {
+            let level = if buy {
+                self.asks.keys().next().copied()
+            } else {
+                self.bids.keys().next_back().copied()
+            };
+            let Some(ticks) = level else { break };
+
+            let crosses = match limit_ticks {
+                None => true,
+                Some(limit) if buy => ticks <= limit,
+                Some(limit) => ticks >= limit,
+            };
+            if !crosses {
+                break;
+            }
+
+            let side = if buy { &mut self.asks } else { &mut self.bids };
+            let queue = side.get_mut(&ticks).expect("level exists");
+            while remaining > 0 {
+                let Some(resting) = queue.front_mut() else { break };
+                let traded = remaining.min(resting.quantity);
+                resting.quantity -= traded;
+                remaining -= traded;
+                fills.push(Fill {
+                    resting: RestingOrder { quantity: traded, ..resting.clone() },
+                    price: from_ticks(ticks),
+                });
+                if resting.quantity == 0 {
+                    queue.pop_front();
+                }
+            }
+            if queue.is_empty() {
+                side.remove(&ticks);
+            }
+        }
+
+        fills
+    }
+
+    // Remove the highest-priority resting order on one side ("Buy" or "Sell") whose limit crosses the given price
+    pub fn pop_crossed(&mut self, side: &str, price: f64) -> Option<(f64, RestingOrder)> {
+        let ticks = to_ticks(price);
+        let buy = side == "Buy";
+        let level = if buy {
+            self.bids.range(ticks..).next_back().map(|(t, _)| *t)
+        } else {
+            self.asks.range(..=ticks).next().map(|(t, _)| *t)
+        }?;
+
+        let levels = if buy { &mut self.bids } else { &mut self.asks };
+        let queue = levels.get_mut(&level)?;
+        let order = queue.pop_front()?;
+        if queue.is_empty() {
+            levels.remove(&level);
+        }
+        Some((from_ticks(level), order))
+    }
+
+    // Put an order back at the front of its level, keeping its time priority
+    pub fn push_front(&mut self, price: f64, order: RestingOrder) {
+        let side = if is_buy(&order.action) { &mut self.bids } else { &mut self.asks };
+        side.entry(to_ticks(price)).or_default().push_front(order);
+    }
+
+    // Remove a resting order at its client's request
+    pub fn cancel(&mut self, session: &str, order_id: u32, client_id: u32) -> Option<(f64, RestingOrder)> {
+        let owned = |o: &RestingOrder| o.session == session && o.order_id == order_id && o.client_id == client_id;
+        for side in [&mut self.bids, &mut self.asks] {
+            let found = side
+                .iter()
+                .find_map(|(ticks, queue)| queue.iter().position(owned).map(|i| (*ticks, i)));
+            if let Some((ticks, index)) = found {
+                let queue = side.get_mut(&ticks).expect("level exists");
+                let order = queue.remove(index)?;
+                if queue.is_empty() {
+                    side.remove(&ticks);
+                }
+                return Some((from_ticks(ticks), order));
+            }
+        }
+        None
+    }
+
+    // Remove every resting order, e.g. when the closing auction takes over the book
+    pub fn expire_all(&mut self) -> Vec<(f64, RestingOrder)> {
+        let bids = std::mem::take(&mut self.bids);
+        let asks = std::mem::take(&mut self.asks);
+        bids.into_iter()
+            .chain(asks)
+            .flat_map(|(t, q)| q.into_iter().map(move |o| (from_ticks(t), o)))
+            .collect()
+    }
+
+    // Remove the day orders at the close; good-till-cancelled orders keep their place
+    pub fn expire_day_orders(&mut self) -> Vec<(f64, RestingOrder)> {
+        let mut expired = Vec::new();
+        for side in [&mut self.bids, &mut self.asks] {
+            for (ticks, queue) in side.iter_mut() {
+                let (gtc, day): (VecDeque<_>, VecDeque<_>) = std::mem::take(queue).into_iter().partition(|o| o.is_gtc());
+                expired.extend(day.into_iter().map(|o| (from_ticks(*ticks), o)));
+                *queue = gtc;
+            }
+            side.retain(|_, queue| !queue.is_empty());
+        }
+        expired
+    }
+
+    // Every resting order with its limit price
+    pub fn resting_orders(&self) -> Vec<(f64, RestingOrder)> {
+        self.bids
+            .iter()
+            .chain(self.asks.iter())
+            .flat_map(|(t, q)| q.iter().map(move |o| (from_ticks(*t), o.clone())))
+            .collect()
+    }
+
+    // Whether every resting quantity still fits once scaled by the split
+    pub fn split_fits(&self, new_shares: u32, old_shares: u32) -> bool {
+        self.bids
+            .values()
+            .chain(self.asks.values())
+            .flatten()
+            .all(|order| split_shares(order.quantity, new_shares, old_shares).is_some())
+    }
+
+    // Split `new_shares` for every `old_shares`: limit prices scale down and quantities up.
+    // Orders left with less than one share after a reverse split are cancelled and returned.
+    // The caller checks `split_fits` first.
+    pub fn apply_split(&mut self, new_shares: u32, old_shares: u32) -> Vec<(f64, RestingOrder)> {
+        let ratio = new_shares as f64 / old_shares as f64;
+        let mut cancelled = Vec::new();
+        for (limit, mut order) in self.expire_all() {
+            let quantity = split_shares(order.quantity, new_shares, old_shares).expect("checked by split_fits");
+            if quantity == 0 {
+                cancelled.push((limit, order));
+            } else {
+                order.quantity = quantity;
+                self.add_limit(limit / ratio, order);
+            }
+        }
+        cancelled
+    }
+
+    // Resting buy limits are reduced by the dividend on the ex-date
+    pub fn apply_dividend(&mut self, amount: f64) {
+        for (limit, order) in std::mem::take(&mut self.bids)
+            .into_iter()
+            .flat_map(|(t, q)| q.into_iter().map(move |o| (from_ticks(t), o)))
+        {
+            self.add_limit((limit - amount).max(0.01), order);
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<DepthLevel> {
+        self.bids.iter().next_back().map(|(t, q)| aggregate(*t, q))
+    }
+
+    pub fn best_ask(&self) -> Option<DepthLevel> {
+        self.asks.iter().next().map(|(t, q)| aggregate(*t, q))
+    }
+
+    // Aggregated top-N levels of each side, best price first
+    pub fn depth(&self, levels: usize) -> (Vec<DepthLevel>, Vec<DepthLevel>) {
+        let bids = self.bids.iter().rev().take(levels).map(|(t, q)| aggregate(*t, q)).collect();
+        let asks = self.asks.iter().take(levels).map(|(t, q)| aggregate(*t, q)).collect();
+        (bids, asks)
+    }
+}
+
+fn aggregate(ticks: u64, queue: &VecDeque<RestingOrder>) -> DepthLevel {
+    DepthLevel {
+        price: from_ticks(ticks),
+        quantity: queue.iter().map(|o| o.quantity).sum(),
+    }
+}
+
+// Tracks what was last published per symbol so only changed levels go out on the feed
+#[derive(Default)]
+pub struct DepthFeed {
+    published: HashMap<String, PublishedDepth>,
+}
+
+#[derive(Default)]
+struct PublishedDepth {
+    seq: u64,
+    bids: Vec<DepthLevel>,
+    asks: Vec<DepthLevel>,
+    top: (Option<DepthLevel>, Option<DepthLevel>),
+}
+
+impl DepthFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Incremental updates (and a new top of book, if it moved) since the last publish
+    pub fn updates(&mut self, stock: &str, book: &OrderBook) -> Vec<DepthMessage> {
+        let published = self.published.entry(stock.to_string()).or_default();
+        let (bids, asks) = book.depth(DEPTH_LEVELS);
+        let mut messages = Vec::new();
+
+        for (side, old, new) in [("Buy", &published.bids, &bids), ("Sell", &published.asks, &asks)] {
+            for level in old.iter().filter(|o| !new.iter().any(|n| n.price == o.price)) {
+                published.seq += 1;
+                messages.push(DepthMessage::Update {
+                    stock: stock.to_string(),
+                    seq: published.seq,
+                    side: side.to_string(),
+                    price: level.price,
+                    quantity: 0,
+                });
+            }
+            for level in new.iter().filter(|n| !old.contains(n)) {
+                published.seq += 1;
+                messages.push(DepthMessage::Update {
+                    stock: stock.to_string(),
+                    seq: published.seq,
+                    side: side.to_string(),
+                    price: level.price,
+                    quantity: level.quantity,
+                });
+            }
+        }
+        published.bids = bids;
+        published.asks = asks;
+
+        let top = (book.best_bid(), book.best_ask());
+        if top != published.top {
+            published.top = top.clone();
+            messages.push(DepthMessage::TopOfBook {
+                stock: stock.to_string(),
+                seq: published.seq,
+                bid: top.0,
+                ask: top.1,
+            });
+        }
+
+        messages
+    }
+
+    // Full picture of the book at the current sequence number
+    pub fn snapshot(&self, stock: &str, book: &OrderBook) -> DepthMessage {
+        let (bids, asks) = book.depth(DEPTH_LEVELS);
+
+        DepthMessage::Snapshot {
+            stock: stock.to_string(),
+            seq: self.published.get(stock).map(|p| p.seq).unwrap_or(0),
+            bids,
+            asks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rest(book: &mut OrderBook, order_id: u32, action: &str, price: f64, quantity: u32) {
+        let order = RestingOrder {
+            order_id,
+            client_id: order_id,
+            broker_id: 1,
+            session: String::new(),
+            exchange_id: String::new(),
+            action: action.to_string(),
+            quantity,
+            time_in_force: "Day".to_string(),
+        };
+        book.add_limit(price, order);
+    }
+
+    fn traded(fills: &[Fill]) -> Vec<(u32, u32, f64)> {
+        fills.iter().map(|fill| (fill.resting.order_id, fill.resting.quantity, fill.price)).collect()
+    }
+
+    #[test]
+    fn better_price_then_earlier_order_fills_first() {
+        let mut book = OrderBook::new();
+        rest(&mut book, 1, "Sell", 10.00, 100);
+        rest(&mut book, 2, "Sell", 10.00, 100);
+        rest(&mut book, 3, "Sell", 9.99, 100);
+
+        let fills = book.match_order("Buy", 250, None);
+
+        assert_eq!(traded(&fills), vec![(3, 100, 9.99), (1, 100, 10.00), (2, 50, 10.00)]);
+        assert_eq!(book.best_ask(), Some(DepthLevel { price: 10.00, quantity: 50 }));
+    }
+
+    #[test]
+    fn partially_filled_order_keeps_its_place() {
+        let mut book = OrderBook::new();
+        rest(&mut book, 1, "Buy", 10.00, 100);
+        rest(&mut book, 2, "Buy", 10.00, 100);
+
+        let fills = book.match_order("Sell", 30, Some(10.00));
+        assert_eq!(traded(&fills), vec![(1, 30, 10.00)]);
+
+        let fills = book.match_order("Sell", 100, Some(10.00));
+        assert_eq!(traded(&fills), vec![(1, 70, 10.00), (2, 30, 10.00)]);
+        assert_eq!(book.best_bid(), Some(DepthLevel { price: 10.00, quantity: 70 }));
+    }
+
+    #[test]
+    fn limit_stops_matching_at_its_price() {
+        let mut book = OrderBook::new();
+        rest(&mut book, 1, "Sell", 10.00, 100);
+        rest(&mut book, 2, "Sell", 10.05, 100);
+
+        let fills = book.match_order("Buy", 150, Some(10.00));
+
+        assert_eq!(traded(&fills), vec![(1, 100, 10.00)]);
+        assert_eq!(book.best_ask(), Some(DepthLevel { price: 10.05, quantity: 100 }));
+        assert!(book.match_order("Buy", 100, Some(9.99)).is_empty());
+    }
+
+    #[test]
+    fn cancel_only_removes_the_clients_own_order() {
+        let mut book = OrderBook::new();
+        rest(&mut book, 1, "Buy", 9.99, 100);
+        rest(&mut book, 2, "Buy", 9.98, 100);
+
+        assert!(book.cancel("", 1, 2).is_none());
+        assert!(book.cancel("other-session", 1, 1).is_none());
+        let (price, order) = book.cancel("", 1, 1).expect("own order cancels");
+
+        assert_eq!((price, order.order_id, order.quantity), (9.99, 1, 100));
+        assert_eq!(book.best_bid(), Some(DepthLevel { price: 9.98, quantity: 100 }));
+        assert!(book.cancel("", 1, 1).is_none());
+    }
+}
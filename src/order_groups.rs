@@ -0,0 +1,395 @@
+use crate::brokers::Order;
+use crate::market_data::{is_buy, ExecutionReport};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Order types a broker manages as linked legs
+pub fn is_group(order_type: &str) -> bool {
+    matches!(order_type, "OCO" | "Bracket")
+}
+
+// Prices of the linked exits. An OCO pair is a take-profit limit and a stop-loss for the order's own
+// action and quantity; a bracket puts the same pair on the opposite side of whatever its entry fills.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LinkedPrices {
+    pub take_profit: f64,         // Limit price of the exit that takes the gain
+    pub stop_loss: f64,           // Once the last price reaches it, the protective exit goes out at market
+    pub entry_order_type: String, // Bracket entry: "Limit" at the order's price, or "Market"
+}
+
+impl Default for LinkedPrices {
+    fn default() -> Self {
+        Self { take_profit: 0.0, stop_loss: 0.0, entry_order_type: "Limit".to_string() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Leg {
+    Entry,
+    TakeProfit,
+    StopLoss,
+}
+
+// Child orders sent for one leg and what they have filled
+#[derive(Debug, Default)]
+struct LegState {
+    working: HashMap<u32, u32>, // Child order id to what is still open of it
+    filled: u32,
+}
+
+impl LegState {
+    fn open(&self) -> u32 {
+        self.working.values().sum()
+    }
+}
+
+// An OCO pair or a bracket a broker is managing
+pub struct OrderGroup {
+    pub order: Order,
+    prices: LinkedPrices,
+    entry: LegState,
+    take_profit: LegState,
+    stop_loss: LegState,
+    entry_sent: bool,
+    pub triggered: bool, // The stop price was reached; the take-profit is withdrawn and the rest exits at market
+    pub finishing: Option<(String, String)>, // Final status and reason once the group is being wound up
+    pub held_stop: Option<u32>, // Id kept for the stop while it waits at the broker for its price
+}
+
+impl OrderGroup {
+    pub fn new(mut order: Order) -> Self {
+        let prices = order.links.take().unwrap_or_default();
+        Self {
+            order,
+            prices,
+            entry: LegState::default(),
+            take_profit: LegState::default(),
+            stop_loss: LegState::default(),
+            entry_sent: false,
+            triggered: false,
+            finishing: None,
+            held_stop: None,
+        }
+    }
+
+    fn is_bracket(&self) -> bool {
+        self.order.order_type == "Bracket"
+    }
+
+    // The exits close what a bracket's entry opened; an OCO pair trades the order's own action
+    fn exit_action(&self) -> &str {
+        if !self.is_bracket() {
+            return &self.order.action;
+        }
+        match self.order.action.as_str() {
+            "Buy" => "Sell",
+            "SellShort" => "BuyToCover",
+            "Sell" => "Buy",
+            _ => "SellShort",
+        }
+    }
+
+    // Shares the exits are there to trade: whatever the entry has filled so far for a bracket
+    fn exit_quantity(&self) -> u32 {
+        if self.is_bracket() {
+            self.entry.filled
+        } else {
+            self.order.quantity
+        }
+    }
+
+    fn exits_filled(&self) -> u32 {
+        self.take_profit.filled + self.stop_loss.filled
+    }
+
+    // Shares of the order itself still to trade: a bracket's entry, or the one exit of an OCO pair
+    pub fn remaining(&self) -> u32 {
+        let filled = if self.is_bracket() { self.entry.filled } else { self.exits_filled() };
+        self.order.quantity.saturating_sub(filled)
+    }
+
+    // A bracket's exits close the position its entry opened, so the client sees them as orders of
+    // their own rather than as more of the bracket
+    pub fn is_own_order(&self, leg: Leg) -> bool {
+        self.is_bracket() && leg != Leg::Entry
+    }
+
+    pub fn working_children(&self) -> Vec<u32> {
+        [&self.entry, &self.take_profit, &self.stop_loss]
+            .iter()
+            .flat_map(|leg| leg.working.keys().copied())
+            .collect()
+    }
+
+    // Nothing working and nothing more to send
+    pub fn is_done(&self) -> bool {
+        self.working_children().is_empty() && self.due().is_empty()
+    }
+
+    // The stop fires once the last price trades through it against the position the exits protect.
+    // Returns the entry and take-profit children to withdraw.
+    pub fn check_stop(&mut self, last: f64) -> Option<Vec<u32>> {
+        if self.triggered || self.finishing.is_some() || !self.has_stop() || last <= 0.0 {
+            return None;
+        }
+        if self.exit_quantity() <= self.exits_filled() {
+            return None;
+        }
+        let reached = if is_buy(self.exit_action()) { last >= self.prices.stop_loss } else { last <= self.prices.stop_loss };
+        if !reached {
+            return None;
+        }
+        self.triggered = true;
+        Some(self.entry.working.keys().chain(self.take_profit.working.keys()).copied().collect())
+    }
+
+    // Child orders to send now, by leg and quantity
+    pub fn due(&self) -> Vec<(Leg, u32)> {
+        let mut due = Vec::new();
+        if self.finishing.is_some() {
+            return due;
+        }
+        if self.is_bracket() && !self.entry_sent {
+            due.push((Leg::Entry, self.order.quantity));
+        }
+        let unexited = self.exit_quantity().saturating_sub(self.exits_filled());
+        if !self.triggered {
+            // The take-profit covers every share the exits have yet to trade; the stop is held here
+            let quantity = unexited.saturating_sub(self.take_profit.open());
+            if quantity > 0 && self.prices.take_profit > 0.0 {
+                due.push((Leg::TakeProfit, quantity));
+            }
+        } else if self.entry.working.is_empty() && self.take_profit.working.is_empty() {
+            // Only once the withdrawn legs are gone, so the stop never trades shares the take-profit also sells
+            let quantity = unexited.saturating_sub(self.stop_loss.open());
+            if quantity > 0 {
+                due.push((Leg::StopLoss, quantity));
+            }
+        }
+        due
+    }
+
+    pub fn child(&mut self, leg: Leg, order_id: u32, quantity: u32) -> Order {
+        if leg == Leg::Entry {
+            self.entry_sent = true;
+        }
+        self.leg_mut(leg).working.insert(order_id, quantity);
+        self.leg_order(leg, order_id, quantity)
+    }
+
+    pub fn has_stop(&self) -> bool {
+        self.prices.stop_loss > 0.0
+    }
+
+    // The stop as it would go out, while it is held here; a bracket's covers its whole entry
+    pub fn stop_order(&self, order_id: u32) -> Order {
+        self.leg_order(Leg::StopLoss, order_id, self.order.quantity)
+    }
+
+    fn leg_order(&self, leg: Leg, order_id: u32, quantity: u32) -> Order {
+        let (action, order_type, price) = match leg {
+            Leg::Entry => (self.order.action.clone(), self.prices.entry_order_type.clone(), self.order.price),
+            Leg::TakeProfit => (self.exit_action().to_string(), "Limit".to_string(), self.prices.take_profit),
+            Leg::StopLoss => (self.exit_action().to_string(), "Market".to_string(), self.prices.stop_loss),
+        };
+        Order {
+            order_id,
+            action,
+            quantity,
+            price,
+            order_type,
+            venue: String::new(),
+            links: None,
+            ..self.order.clone()
+        }
+    }
+
+    fn leg_mut(&mut self, leg: Leg) -> &mut LegState {
+        match leg {
+            Leg::Entry => &mut self.entry,
+            Leg::TakeProfit => &mut self.take_profit,
+            Leg::StopLoss => &mut self.stop_loss,
+        }
+    }
+
+    // Track a child's report. A fill of the order's own leg comes back as a fill of the group; a
+    // bracket's exits come back as they are, except that the broker already acknowledged them when they
+    // were sent, and a refused one ends as a cancel of an order the client saw accepted.
+    pub fn on_child_report(&mut self, report: &ExecutionReport) -> Option<ExecutionReport> {
+        let leg = [Leg::Entry, Leg::TakeProfit, Leg::StopLoss]
+            .into_iter()
+            .find(|leg| self.leg_mut(*leg).working.contains_key(&report.order_id))?;
+        if report.status == "Accepted" {
+            return None;
+        }
+        let order_id = self.order.order_id;
+        let own_order = self.is_own_order(leg);
+        let state = self.leg_mut(leg);
+        if report.status == "Filled" {
+            state.filled += report.quantity;
+        }
+        if let Some(open) = state.working.get_mut(&report.order_id) {
+            *open = open.saturating_sub(report.quantity);
+            if *open == 0 {
+                state.working.remove(&report.order_id);
+            }
+        }
+        match report.status.as_str() {
+            "Rejected" if own_order => Some(ExecutionReport {
+                status: "Cancelled".to_string(),
+                reason: Some(format!("rejected: {}", report.reason.as_deref().unwrap_or("-"))),
+                ..report.clone()
+            }),
+            _ if own_order => Some(report.clone()),
+            "Filled" => Some(ExecutionReport { order_id, ..report.clone() }),
+            _ => None,
+        }
+    }
+
+    pub fn progress(&self) -> String {
+        let entry = if self.is_bracket() {
+            format!("entry {}/{}, ", self.entry.filled, self.order.quantity)
+        } else {
+            String::new()
+        };
+        format!(
+            "{} order {} {} {}: {}take profit {} @ {:.2}, stop loss {} @ {:.2}{}",
+            self.order.order_type,
+            self.order.order_id,
+            self.order.action,
+            self.order.stock,
+            entry,
+            self.take_profit.filled,
+            self.prices.take_profit,
+            self.stop_loss.filled,
+            self.prices.stop_loss,
+            if self.triggered { " (stopped)" } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(order_type: &str, action: &str, quantity: u32) -> OrderGroup {
+        OrderGroup::new(Order {
+            order_id: 1,
+            client_id: 7,
+            broker_id: 1,
+            session: String::new(),
+            stock: "AAPL".to_string(),
+            action: action.to_string(),
+            quantity,
+            price: 10.0,
+            order_type: order_type.to_string(),
+            time_in_force: "Day".to_string(),
+            venue: String::new(),
+            algo: None,
+            links: Some(LinkedPrices { take_profit: 11.0, stop_loss: 9.0, ..LinkedPrices::default() }),
+        })
+    }
+
+    fn report(order_id: u32, status: &str, quantity: u32) -> ExecutionReport {
+        ExecutionReport {
+            order_id,
+            client_id: 7,
+            stock: "AAPL".to_string(),
+            action: "Sell".to_string(),
+            status: status.to_string(),
+            quantity,
+            price: 10.0,
+            reason: None,
+            broker_id: 1,
+            fee: 0.0,
+            liquidity: None,
+            venue: String::new(),
+            session: String::new(),
+            exchange_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn oco_exits_never_trade_more_than_the_order() {
+        let mut oco = group("OCO", "Sell", 100);
+        assert_eq!(oco.due(), vec![(Leg::TakeProfit, 100)]);
+        let take_profit = oco.child(Leg::TakeProfit, 201, 100);
+        assert_eq!((take_profit.action.as_str(), take_profit.order_type.as_str(), take_profit.price), ("Sell", "Limit", 11.0));
+
+        let fill = oco.on_child_report(&report(201, "Filled", 40)).expect("group fill");
+        assert_eq!((fill.order_id, fill.quantity), (1, 40));
+        assert_eq!(oco.remaining(), 60);
+
+        // The stop withdraws the take-profit and only goes out once it is gone
+        assert_eq!(oco.check_stop(9.5), None);
+        assert_eq!(oco.check_stop(9.0), Some(vec![201]));
+        assert!(oco.due().is_empty());
+        oco.on_child_report(&report(201, "Cancelled", 60));
+        assert_eq!(oco.due(), vec![(Leg::StopLoss, 60)]);
+
+        oco.child(Leg::StopLoss, 202, 60);
+        oco.on_child_report(&report(202, "Filled", 60));
+        assert_eq!(oco.remaining(), 0);
+        assert!(oco.is_done());
+    }
+
+    #[test]
+    fn bracket_exits_cover_what_the_entry_has_filled() {
+        let mut bracket = group("Bracket", "Buy", 100);
+        assert_eq!(bracket.due(), vec![(Leg::Entry, 100)]);
+        bracket.child(Leg::Entry, 201, 100);
+        assert!(bracket.due().is_empty());
+
+        bracket.on_child_report(&report(201, "Filled", 30));
+        assert_eq!(bracket.remaining(), 70);
+        assert_eq!(bracket.due(), vec![(Leg::TakeProfit, 30)]);
+        let exit = bracket.child(Leg::TakeProfit, 202, 30);
+        assert_eq!((exit.action.as_str(), exit.price), ("Sell", 11.0));
+
+        bracket.on_child_report(&report(201, "Filled", 70));
+        assert_eq!(bracket.due(), vec![(Leg::TakeProfit, 70)]);
+        bracket.child(Leg::TakeProfit, 203, 70);
+
+        assert_eq!(bracket.remaining(), 0);
+
+        // The exits close the position as orders of their own
+        let exit_fill = bracket.on_child_report(&report(202, "Filled", 30)).expect("exit fill");
+        assert_eq!((exit_fill.order_id, exit_fill.quantity), (202, 30));
+        bracket.on_child_report(&report(203, "Filled", 70));
+        assert!(bracket.is_done());
+    }
+
+    #[test]
+    fn a_rejected_bracket_exit_ends_as_a_cancel() {
+        let mut bracket = group("Bracket", "Buy", 100);
+        bracket.child(Leg::Entry, 201, 100);
+        bracket.on_child_report(&report(201, "Filled", 100));
+        bracket.child(Leg::TakeProfit, 202, 100);
+
+        let rejected = ExecutionReport { reason: Some("Unknown stock".to_string()), ..report(202, "Rejected", 100) };
+        let cancel = bracket.on_child_report(&rejected).expect("exit report");
+
+        assert_eq!((cancel.order_id, cancel.status.as_str()), (202, "Cancelled"));
+        assert_eq!(cancel.reason.as_deref(), Some("rejected: Unknown stock"));
+        assert!(bracket.working_children().is_empty());
+    }
+
+    #[test]
+    fn bracket_stop_exits_only_the_filled_part_of_the_entry() {
+        let mut bracket = group("Bracket", "Buy", 100);
+        bracket.child(Leg::Entry, 201, 100);
+        bracket.on_child_report(&report(201, "Filled", 50));
+        bracket.child(Leg::TakeProfit, 202, 50);
+
+        let mut withdrawn = bracket.check_stop(9.0).expect("stop reached");
+        withdrawn.sort();
+        assert_eq!(withdrawn, vec![201, 202]);
+        bracket.on_child_report(&report(201, "Cancelled", 50));
+        assert!(bracket.due().is_empty());
+        bracket.on_child_report(&report(202, "Cancelled", 50));
+
+        assert_eq!(bracket.due(), vec![(Leg::StopLoss, 50)]);
+        assert_eq!(bracket.check_stop(8.0), None);
+    }
+}
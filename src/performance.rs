@@ -0,0 +1,127 @@
+use crate::market_data::ExecutionReport;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// One fill in a backtest's trade log
+#[derive(Serialize, Debug, Clone)]
+pub struct TradeLogEntry {
+    pub timestamp: u64, // Data time of the fill, in milliseconds since the Unix epoch
+    pub client_id: u32,
+    pub order_id: u32,
+    pub broker_id: u32,
+    pub stock: String,
+    pub action: String,
+    pub quantity: u32,
+    pub price: f64,
+    pub fee: f64,
+    pub liquidity: Option<String>,
+}
+
+// How one strategy did over the backtest
+#[derive(Serialize, Debug, Clone)]
+pub struct ClientPerformance {
+    pub client_id: u32,
+    pub strategy: String,
+    pub starting_equity: f64,
+    pub final_equity: f64,
+    pub total_return: f64, // Fraction of the starting equity
+    pub sharpe_ratio: f64, // Annualized, on the returns between equity samples
+    pub max_drawdown: f64, // Largest fall from a peak, as a fraction of the peak
+    pub turnover: f64,     // Value traded over the starting equity
+    pub trades: usize,
+    pub fees: f64,
+}
+
+// Equity curves and fills of every strategy in a backtest
+#[derive(Debug, Default)]
+pub struct Performance {
+    strategies: BTreeMap<u32, String>,
+    equity: BTreeMap<u32, Vec<f64>>,
+    traded_value: BTreeMap<u32, f64>,
+    trades: Vec<TradeLogEntry>,
+}
+
+impl Performance {
+    pub fn new(strategies: BTreeMap<u32, String>) -> Self {
+        Self { strategies, ..Default::default() }
+    }
+
+    pub fn record_fill(&mut self, timestamp: u64, report: &ExecutionReport) {
+        *self.traded_value.entry(report.client_id).or_insert(0.0) += report.quantity as f64 * report.price;
+        self.trades.push(TradeLogEntry {
+            timestamp,
+            client_id: report.client_id,
+            order_id: report.order_id,
+            broker_id: report.broker_id,
+            stock: report.stock.clone(),
+            action: report.action.clone(),
+            quantity: report.quantity,
+            price: report.price,
+            fee: report.fee,
+            liquidity: report.liquidity.clone(),
+        });
+    }
+
+    pub fn sample(&mut self, client_id: u32, equity: f64) {
+        self.equity.entry(client_id).or_default().push(equity);
+    }
+
+    pub fn trades(&self) -> &[TradeLogEntry] {
+        &self.trades
+    }
+
+    pub fn summary(&self, periods_per_year: f64) -> Vec<ClientPerformance> {
+        self.strategies
+            .iter()
+            .map(|(client_id, strategy)| {
+                let curve = self.equity.get(client_id).map(Vec::as_slice).unwrap_or(&[]);
+                let starting_equity = curve.first().copied().unwrap_or(0.0);
+                let final_equity = curve.last().copied().unwrap_or(0.0);
+                let fills = self.trades.iter().filter(|t| t.client_id == *client_id);
+
+                ClientPerformance {
+                    client_id: *client_id,
+                    strategy: strategy.clone(),
+                    starting_equity,
+                    final_equity,
+                    total_return: ratio(final_equity - starting_equity, starting_equity),
+                    sharpe_ratio: sharpe_ratio(curve, periods_per_year),
+                    max_drawdown: max_drawdown(curve),
+                    turnover: ratio(self.traded_value.get(client_id).copied().unwrap_or(0.0), starting_equity),
+                    trades: fills.clone().count(),
+                    fees: fills.map(|t| t.fee).sum(),
+                }
+            })
+            .collect()
+    }
+}
+
+fn ratio(value: f64, base: f64) -> f64 {
+    if base.abs() > f64::EPSILON {
+        value / base
+    } else {
+        0.0
+    }
+}
+
+// Mean over standard deviation of the period returns, scaled to a year; zero without any variation
+fn sharpe_ratio(curve: &[f64], periods_per_year: f64) -> f64 {
+    let returns: Vec<f64> = curve.windows(2).map(|pair| ratio(pair[1] - pair[0], pair[0])).collect();
+    if returns.len() < 2 {
+        return 0.0;
+    }
+    let count = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / count;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (count - 1.0);
+    ratio(mean, variance.sqrt()) * periods_per_year.sqrt()
+}
+
+fn max_drawdown(curve: &[f64]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut drawdown: f64 = 0.0;
+    for &equity in curve {
+        peak = peak.max(equity);
+        drawdown = drawdown.max(ratio(peak - equity, peak));
+    }
+    drawdown
+}
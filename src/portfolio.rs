@@ -0,0 +1,89 @@
+use crate::market_data::ExecutionReport;
+use std::collections::{BTreeMap, HashMap};
+
+// What one client holds according to the fills reported back by the stock system
+#[derive(Debug, Clone, Default)]
+pub struct ClientPosition {
+    pub cash: f64,
+    pub positions: HashMap<String, i64>, // Positive when long, negative when short
+}
+
+impl ClientPosition {
+    pub fn position(&self, stock: &str) -> i64 {
+        self.positions.get(stock).copied().unwrap_or(0)
+    }
+}
+
+// Positions of every client the trader sends orders for
+#[derive(Debug, Default)]
+pub struct Portfolio {
+    clients: BTreeMap<u32, ClientPosition>,
+    starting_cash: f64, // Cash each client's account opens with
+}
+
+impl Portfolio {
+    pub fn new(starting_cash: f64) -> Self {
+        Self { clients: BTreeMap::new(), starting_cash }
+    }
+
+    // Update cash and positions from a fill; other reports leave the portfolio unchanged
+    pub fn apply(&mut self, report: &ExecutionReport) {
+        if report.status != "Filled" {
+            return;
+        }
+        let starting_cash = self.starting_cash;
+        let client = self
+            .clients
+            .entry(report.client_id)
+            .or_insert_with(|| ClientPosition { cash: starting_cash, ..Default::default() });
+        let value = report.quantity as f64 * report.price;
+        client.cash -= report.fee;
+        let position = client.positions.entry(report.stock.clone()).or_insert(0);
+
+        match report.action.as_str() {
+            "Buy" | "BuyToCover" => {
+                client.cash -= value;
+                *position += report.quantity as i64;
+            }
+            _ => {
+                client.cash += value;
+                *position -= report.quantity as i64;
+            }
+        }
+        if *position == 0 {
+            client.positions.remove(&report.stock);
+        }
+    }
+
+    // Scale positions for a split; fractional shares are paid out in cash by the stock system
+    pub fn apply_split(&mut self, stock: &str, new_shares: u32, old_shares: u32) {
+        for client in self.clients.values_mut() {
+            if let Some(position) = client.positions.get_mut(stock) {
+                *position = *position * new_shares as i64 / old_shares as i64;
+                if *position == 0 {
+                    client.positions.remove(stock);
+                }
+            }
+        }
+    }
+
+    pub fn rename_symbol(&mut self, old: &str, new: &str) {
+        for client in self.clients.values_mut() {
+            if let Some(position) = client.positions.remove(old) {
+                client.positions.insert(new.to_string(), position);
+            }
+        }
+    }
+
+    pub fn client(&self, client_id: u32) -> ClientPosition {
+        self.clients
+            .get(&client_id)
+            .cloned()
+            .unwrap_or_else(|| ClientPosition { cash: self.starting_cash, ..Default::default() })
+    }
+
+    // Clients that have traded, ordered by client id
+    pub fn iter(&self) -> impl Iterator<Item = (&u32, &ClientPosition)> {
+        self.clients.iter()
+    }
+}
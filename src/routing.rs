@@ -0,0 +1,160 @@
+use crate::blotter::{BlotterEntry, BLOTTER_DIR};
+use crate::brokers::{Broker, Order};
+use crate::fees::FeeSchedules;
+use crate::market_data::{ExecutionReport, TradePrint};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+// Default location of the broker routing settings
+pub const ROUTING_PATH: &str = "config/routing.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RoutingConfig {
+    pub broker_count: u32,
+    pub policy: String, // "Assigned", "RoundRobin", "LeastLoaded" or "BestFee"
+    pub assignments: HashMap<u32, u32>, // Client to broker; other clients are spread over the brokers by id
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self { broker_count: 3, policy: "Assigned".to_string(), assignments: HashMap::new() }
+    }
+}
+
+impl RoutingConfig {
+    // Load the settings from disk, falling back to the defaults
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                println!("[Routing] Invalid config {}: {}, using defaults", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+// Picks the broker for each order and remembers it, so a cancel goes down the same queue as its order
+pub struct Router {
+    config: RoutingConfig,
+    fees: FeeSchedules,
+    brokers: Vec<Broker>,
+    next: usize,               // Round-robin position
+    routes: HashMap<u32, u32>, // Order id to broker id
+}
+
+impl Router {
+    pub fn new(config: RoutingConfig, fees: FeeSchedules, brokers: Vec<Broker>) -> Self {
+        assert!(!brokers.is_empty(), "Routing needs at least one broker");
+        Self { config, fees, brokers, next: 0, routes: HashMap::new() }
+    }
+
+    pub fn brokers(&self) -> &[Broker] {
+        &self.brokers
+    }
+
+    // A client's orders across every broker, in order id order
+    pub fn client_blotter(&self, client_id: u32) -> Vec<&BlotterEntry> {
+        let mut entries: Vec<&BlotterEntry> =
+            self.brokers.iter().flat_map(|broker| broker.blotter.for_client(client_id)).collect();
+        entries.sort_by_key(|entry| entry.order.order_id);
+        entries
+    }
+
+    // Write each broker's blotter to its own file for the day
+    pub fn export_blotters(&self, date: &str) {
+        for broker in &self.brokers {
+            let path = Path::new(BLOTTER_DIR).join(format!("blotter_{}_broker_{}.json", date, broker.id));
+            match broker.blotter.export(&path) {
+                Ok(()) => println!("[Blotter] Broker {} blotter written to {}", broker.id, path.display()),
+                Err(err) => println!("[Blotter] Failed to write broker {} blotter to {}: {}", broker.id, path.display(), err),
+            }
+        }
+    }
+
+    pub fn submit(&mut self, order: Order) {
+        let index = self.choose(&order);
+        self.routes.insert(order.order_id, self.brokers[index].id);
+        self.brokers[index].handle_order(order);
+    }
+
+    pub fn cancel(&mut self, order_id: u32, client_id: u32, stock: &str) {
+        let index = match self.routes.get(&order_id) {
+            Some(broker_id) => self.index_of(*broker_id),
+            None => self.assigned(client_id),
+        };
+        self.brokers[index].cancel_order(order_id, client_id, stock);
+    }
+
+    // Let every broker's algos release their due child orders; returns reports on parent orders
+    pub fn on_timer(&mut self, now: u64) -> Vec<ExecutionReport> {
+        let mut reports = Vec::new();
+        for broker in &mut self.brokers {
+            broker.on_timer(now);
+            reports.extend(broker.take_reports());
+        }
+        reports
+    }
+
+    pub fn on_trade(&mut self, trade: &TradePrint) {
+        for broker in &mut self.brokers {
+            broker.on_trade(trade);
+        }
+    }
+
+    // A report from the stock system as its client should see it, through the broker that sent the order
+    pub fn on_execution(&mut self, report: ExecutionReport) -> Vec<ExecutionReport> {
+        let index = self.index_of(report.broker_id);
+        self.brokers[index].on_execution(report)
+    }
+
+    fn choose(&mut self, order: &Order) -> usize {
+        match self.config.policy.as_str() {
+            "RoundRobin" => {
+                self.next = (self.next + 1) % self.brokers.len();
+                self.next
+            }
+            // Shortest queue, then the broker that has handled the fewest orders
+            "LeastLoaded" => self
+                .brokers
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, broker)| {
+                    let stats = broker.stats.lock().unwrap();
+                    (stats.queued(), stats.routed)
+                })
+                .map(|(index, _)| index)
+                .unwrap_or(0),
+            // Cheapest expected fee: market orders take liquidity, limit orders are expected to add it
+            "BestFee" => {
+                let liquidity = if order.order_type == "Market" { "Taker" } else { "Maker" };
+                let fee = |broker: &Broker| {
+                    let volume = broker.stats.lock().unwrap().shares;
+                    self.fees.for_broker(broker.id).fee(order.quantity, order.price, volume, liquidity)
+                };
+                self.brokers
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| fee(a).total_cmp(&fee(b)))
+                    .map(|(index, _)| index)
+                    .unwrap_or(0)
+            }
+            _ => self.assigned(order.client_id),
+        }
+    }
+
+    // The client's own broker
+    fn assigned(&self, client_id: u32) -> usize {
+        match self.config.assignments.get(&client_id) {
+            Some(broker_id) => self.index_of(*broker_id),
+            None => client_id as usize % self.brokers.len(),
+        }
+    }
+
+    fn index_of(&self, broker_id: u32) -> usize {
+        self.brokers.iter().position(|broker| broker.id == broker_id).unwrap_or(0)
+    }
+}
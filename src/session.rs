@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Default location of the trading calendar
+pub const CALENDAR_PATH: &str = "config/calendar.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    PreOpen,
+    OpeningAuction,
+    Continuous,
+    ClosingAuction,
+    PostClose,
+    Holiday,
+}
+
+impl Phase {
+    // Order types the stock system accepts in this phase
+    pub fn accepts(&self, order_type: &str) -> bool {
+        match self {
+            Phase::PreOpen => order_type == "Limit",
+            Phase::OpeningAuction | Phase::Continuous | Phase::ClosingAuction => {
+                order_type == "Limit" || order_type == "Market"
+            }
+            Phase::PostClose | Phase::Holiday => false,
+        }
+    }
+
+    // Call auction that collects accepted orders during this phase instead of matching them on arrival
+    pub fn auction(&self) -> Option<&'static str> {
+        match self {
+            Phase::PreOpen | Phase::OpeningAuction => Some("Opening"),
+            Phase::ClosingAuction => Some("Closing"),
+            _ => None,
+        }
+    }
+
+    // Prices only move with the market while it trades continuously
+    pub fn is_continuous(&self) -> bool {
+        *self == Phase::Continuous
+    }
+}
+
+// Phase transition, published on the "market_phase" routing key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PhaseChange {
+    pub phase: Phase,
+    pub day: u32,
+    pub date: String,
+    pub duration_secs: u64,
+}
+
+// Length of each phase of the (compressed) trading day, the dates the market stays shut and
+// how many days to simulate
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SessionCalendar {
+    pub pre_open_secs: u64,
+    pub opening_auction_secs: u64,
+    pub continuous_secs: u64,
+    pub closing_auction_secs: u64,
+    pub weekends_closed: bool,
+    pub holidays: Vec<String>,  // "YYYY-MM-DD"
+    pub multi_day: bool,        // Carry the close of each session over to the next, across runs
+    pub days: u32,              // Sessions to run before the process exits
+    pub max_overnight_gap: f64, // Largest overnight price move, as a fraction of the close
+}
+
+impl Default for SessionCalendar {
+    fn default() -> Self {
+        Self {
+            pre_open_secs: 5,
+            opening_auction_secs: 5,
+            continuous_secs: 40,
+            closing_auction_secs: 10,
+            weekends_closed: false,
+            holidays: Vec::new(),
+            multi_day: false,
+            days: 1,
+            max_overnight_gap: 0.03,
+        }
+    }
+}
+
+impl SessionCalendar {
+    // Load the calendar from disk, falling back to the defaults
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                println!("[Session] Invalid calendar {}: {}, using defaults", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn is_holiday(&self, date: &str) -> bool {
+        if self.holidays.iter().any(|h| h == date) {
+            return true;
+        }
+        self.weekends_closed && is_weekend(date)
+    }
+
+    // The trading day `days` trading days after `date`, skipping weekends and holidays
+    pub fn settlement_date(&self, date: &str, days: u32) -> String {
+        let mut settlement = date.to_string();
+        let mut remaining = days;
+        while remaining > 0 {
+            settlement = next_date(&settlement);
+            if !self.is_holiday(&settlement) {
+                remaining -= 1;
+            }
+        }
+        settlement
+    }
+
+    // Phases the market runs through on the given date, in order
+    pub fn schedule(&self, date: &str) -> Vec<(Phase, Duration)> {
+        if self.is_holiday(date) {
+            return vec![(Phase::Holiday, Duration::ZERO)];
+        }
+        vec![
+            (Phase::PreOpen, Duration::from_secs(self.pre_open_secs)),
+            (Phase::OpeningAuction, Duration::from_secs(self.opening_auction_secs)),
+            (Phase::Continuous, Duration::from_secs(self.continuous_secs)),
+            (Phase::ClosingAuction, Duration::from_secs(self.closing_auction_secs)),
+            (Phase::PostClose, Duration::ZERO),
+        ]
+    }
+}
+
+// Today's UTC date as "YYYY-MM-DD"
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// The calendar day after "YYYY-MM-DD"
+pub fn next_date(date: &str) -> String {
+    match days_from_civil(date) {
+        Some(days) => {
+            let (year, month, day) = civil_from_days(days + 1);
+            format!("{:04}-{:02}-{:02}", year, month, day)
+        }
+        None => today(),
+    }
+}
+
+fn is_weekend(date: &str) -> bool {
+    match days_from_civil(date) {
+        // 1970-01-01 was a Thursday
+        Some(days) => matches!((days + 3).rem_euclid(7), 5 | 6),
+        None => false,
+    }
+}
+
+// Days since 1970-01-01 to a (year, month, day) in the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_from_civil(date: &str) -> Option<i64> {
+    let mut parts = date.split('-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some(era * 146_097 + doe - 719_468)
+}
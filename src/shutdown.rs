@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const RUNNING: u8 = 0;
+const CLOSING: u8 = 1;
+const DRAINING: u8 = 2;
+
+// Process-wide close signal. Closing stops intake and timers; draining tells publishers
+// to send whatever is left and exit.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    stage: Arc<AtomicU8>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Ctrl-C or SIGTERM starts the close sequence; a second signal exits immediately
+    pub fn install_signal_handler(&self) {
+        let shutdown = self.clone();
+        ctrlc::set_handler(move || {
+            if shutdown.is_closing() {
+                println!("\n[Shutdown] Second signal received, exiting immediately");
+                std::process::exit(130);
+            }
+            println!("\n[Shutdown] Signal received, closing the market");
+            shutdown.begin_close();
+        })
+        .expect("Failed to install signal handler");
+    }
+
+    pub fn begin_close(&self) {
+        self.stage.fetch_max(CLOSING, Ordering::SeqCst);
+    }
+
+    pub fn begin_drain(&self) {
+        self.stage.fetch_max(DRAINING, Ordering::SeqCst);
+    }
+
+    pub fn is_closing(&self) -> bool {
+        self.stage.load(Ordering::SeqCst) >= CLOSING
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.stage.load(Ordering::SeqCst) >= DRAINING
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.stage.load(Ordering::SeqCst) == RUNNING
+    }
+
+    // Sleep for `duration`, waking early if the close begins. Returns false if it did.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        while self.is_running() {
+            let now = Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            thread::sleep((deadline - now).min(Duration::from_millis(100)));
+        }
+        false
+    }
+}
@@ -0,0 +1,261 @@
+use crate::brokers::Order;
+use crate::fees::FeeSchedules;
+use crate::market_data::{is_buy, ExecutionReport};
+use crate::venues::{ConsolidatedQuotes, VenueConfig};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// What a client holds on one venue. Every venue keeps its own accounts, so a position can only be
+// sold or covered on the venues it was opened on.
+#[derive(Debug, Default, Clone, Copy)]
+struct Holding {
+    long: u32,
+    short: u32,
+}
+
+// Splits a broker's orders across the venues for the best price after venue fees
+pub struct SmartOrderRouter {
+    venues: VenueConfig,
+    fees: Vec<FeeSchedules>, // Per venue, in the same order
+    quotes: Arc<Mutex<ConsolidatedQuotes>>,
+    holdings: HashMap<(u32, String), Vec<Holding>>, // By client and symbol, per venue in the same order
+}
+
+impl SmartOrderRouter {
+    pub fn new(venues: VenueConfig, default_fees: &FeeSchedules, quotes: Arc<Mutex<ConsolidatedQuotes>>) -> Self {
+        let fees = venues.venues.iter().map(|venue| venue.fee_schedules(default_fees)).collect();
+        Self { venues, fees, quotes, holdings: HashMap::new() }
+    }
+
+    // Follow the client's positions on each venue from the fills reported there
+    pub fn on_fill(&mut self, report: &ExecutionReport) {
+        let Some(index) = self.venue_index(&report.venue) else { return };
+        let count = self.venues.venues.len();
+        let holding = &mut self
+            .holdings
+            .entry((report.client_id, report.stock.clone()))
+            .or_insert_with(|| vec![Holding::default(); count])[index];
+        match report.action.as_str() {
+            "Buy" => holding.long += report.quantity,
+            "Sell" => holding.long = holding.long.saturating_sub(report.quantity),
+            "SellShort" => holding.short += report.quantity,
+            _ => holding.short = holding.short.saturating_sub(report.quantity),
+        }
+    }
+
+    // Child orders, at most one per venue, that together make up `order`. Displayed liquidity is taken
+    // from the cheapest venue first, counting the venue's fee and then its latency; whatever is left
+    // goes to the venue where resting is cheapest. A long sale or a cover only goes to the venues
+    // holding the position it closes, and no more to each than it holds there.
+    pub fn split(&self, order: &Order) -> Vec<Order> {
+        if self.venues.venues.len() == 1 {
+            return vec![Order { venue: self.venues.primary().name.clone(), ..order.clone() }];
+        }
+        let limits = self.limits(order);
+        let buying = is_buy(&order.action);
+        let quotes = self.quotes.lock().unwrap();
+
+        // (cost per share, latency, venue index, quantity it can take) for every venue the order can trade on now
+        let mut takers: Vec<(f64, u64, usize, u32)> = Vec::new();
+        for (index, venue) in self.venues.venues.iter().enumerate() {
+            let quote = quotes.venue(&venue.name, &order.stock);
+            let Some(level) = (if buying { quote.ask } else { quote.bid }) else { continue };
+            let marketable = order.order_type == "Market"
+                || if buying { level.price <= order.price } else { level.price >= order.price };
+            let quantity = level.quantity.min(limits[index]);
+            if !marketable || quantity == 0 {
+                continue;
+            }
+            let fee = self.fee_per_share(index, order, level.price, "Taker");
+            let cost = if buying { level.price + fee } else { -(level.price - fee) };
+            takers.push((cost, venue.latency_ms, index, quantity));
+        }
+        drop(quotes);
+        takers.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+        let mut allocations: Vec<(usize, u32)> = Vec::new();
+        let mut remaining = order.quantity;
+        for (_, _, index, displayed) in &takers {
+            if remaining == 0 {
+                break;
+            }
+            let quantity = remaining.min(*displayed);
+            allocations.push((*index, quantity));
+            remaining -= quantity;
+        }
+        while remaining > 0 {
+            let allocated = |index: usize| allocations.iter().find(|(i, _)| *i == index).map_or(0, |(_, q)| *q);
+            let room = |index: usize| limits[index].saturating_sub(allocated(index));
+            // Market orders keep taking on the best venue; limit orders rest where adding liquidity costs least.
+            // With no room left anywhere the rest goes where most is held, and that venue turns it away.
+            let index = match takers.iter().find(|(_, _, index, _)| room(*index) > 0) {
+                Some((_, _, index, _)) if order.order_type == "Market" => *index,
+                _ => self.resting_venue(order, &room).unwrap_or_else(|| most_held(&limits)),
+            };
+            let quantity = match room(index) {
+                0 => remaining,
+                room => remaining.min(room),
+            };
+            match allocations.iter_mut().find(|(i, _)| *i == index) {
+                Some((_, allocated)) => *allocated += quantity,
+                None => allocations.push((index, quantity)),
+            }
+            remaining -= quantity;
+        }
+
+        allocations
+            .into_iter()
+            .map(|(index, quantity)| Order { venue: self.venues.venues[index].name.clone(), quantity, ..order.clone() })
+            .collect()
+    }
+
+    // Shares of the order each venue can take: what a long sale or a cover closes there, or anything.
+    // With no position seen on any venue, e.g. one carried over from an earlier run, it goes to the primary venue.
+    fn limits(&self, order: &Order) -> Vec<u32> {
+        let count = self.venues.venues.len();
+        let holdings = self.holdings.get(&(order.client_id, order.stock.clone()));
+        let held = |side: fn(&Holding) -> u32| holdings.map_or(vec![0; count], |h| h.iter().map(side).collect());
+        let limits = match order.action.as_str() {
+            "Sell" => held(|holding| holding.long),
+            "BuyToCover" => held(|holding| holding.short),
+            _ => return vec![u32::MAX; count],
+        };
+        if limits.iter().all(|limit| *limit == 0) {
+            let mut primary = vec![0; count];
+            primary[0] = u32::MAX;
+            return primary;
+        }
+        limits
+    }
+
+    // The venue with room for the order where resting costs least
+    fn resting_venue(&self, order: &Order, room: &dyn Fn(usize) -> u32) -> Option<usize> {
+        let cost = |index: usize| self.fee_per_share(index, order, order.price, "Maker");
+        let latency = |index: usize| self.venues.venues[index].latency_ms;
+        (0..self.venues.venues.len())
+            .filter(|index| room(*index) > 0)
+            .min_by(|a, b| cost(*a).total_cmp(&cost(*b)).then(latency(*a).cmp(&latency(*b))))
+    }
+
+    fn venue_index(&self, name: &str) -> Option<usize> {
+        if name.is_empty() {
+            return Some(0);
+        }
+        self.venues.venues.iter().position(|venue| venue.name == name)
+    }
+
+    fn fee_per_share(&self, venue_index: usize, order: &Order, price: f64, liquidity: &str) -> f64 {
+        let quantity = order.quantity.max(1);
+        self.fees[venue_index].for_broker(order.broker_id).fee(quantity, price, 0, liquidity) / quantity as f64
+    }
+}
+
+// The venue with the highest limit, the first of them on a tie
+fn most_held(limits: &[u32]) -> usize {
+    (0..limits.len()).max_by_key(|index| (limits[*index], std::cmp::Reverse(*index))).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_data::DepthLevel;
+    use crate::venues::Venue;
+
+    fn venue(name: &str, primary: bool) -> Venue {
+        Venue { name: name.to_string(), latency_ms: 0, fees: None, primary }
+    }
+
+    // Two venues with the same fees; the primary shows the better bid and the other the better offer
+    fn router() -> SmartOrderRouter {
+        let venues = VenueConfig { venues: vec![venue("XNYS", true), venue("BATS", false)] };
+        let mut quotes = ConsolidatedQuotes::default();
+        let level = |price: f64| Some(DepthLevel { price, quantity: 100 });
+        quotes.set_top("XNYS", "AAPL", level(10.10), level(10.20));
+        quotes.set_top("BATS", "AAPL", level(10.00), level(10.15));
+        SmartOrderRouter::new(venues, &FeeSchedules::default(), Arc::new(Mutex::new(quotes)))
+    }
+
+    fn order(action: &str, order_type: &str, quantity: u32, price: f64) -> Order {
+        Order {
+            order_id: 1,
+            client_id: 7,
+            broker_id: 1,
+            session: String::new(),
+            stock: "AAPL".to_string(),
+            action: action.to_string(),
+            quantity,
+            price,
+            order_type: order_type.to_string(),
+            time_in_force: "Day".to_string(),
+            venue: String::new(),
+            algo: None,
+            links: None,
+        }
+    }
+
+    fn fill(action: &str, quantity: u32, venue: &str) -> ExecutionReport {
+        ExecutionReport {
+            order_id: 1,
+            client_id: 7,
+            stock: "AAPL".to_string(),
+            action: action.to_string(),
+            status: "Filled".to_string(),
+            quantity,
+            price: 10.0,
+            reason: None,
+            broker_id: 1,
+            fee: 0.0,
+            liquidity: None,
+            venue: venue.to_string(),
+            session: String::new(),
+            exchange_id: String::new(),
+        }
+    }
+
+    fn venues(children: &[Order]) -> Vec<(&str, u32)> {
+        children.iter().map(|child| (child.venue.as_str(), child.quantity)).collect()
+    }
+
+    #[test]
+    fn buys_take_the_best_offer_first_then_rest() {
+        let router = router();
+
+        let children = router.split(&order("Buy", "Limit", 300, 10.20));
+
+        assert_eq!(venues(&children), vec![("BATS", 100), ("XNYS", 200)]);
+    }
+
+    #[test]
+    fn a_long_sale_only_goes_where_the_shares_are_held() {
+        let mut router = router();
+        router.on_fill(&fill("Buy", 100, "BATS"));
+
+        // The better bid is on the primary venue, which holds none of the shares
+        let children = router.split(&order("Sell", "Limit", 100, 10.00));
+
+        assert_eq!(venues(&children), vec![("BATS", 100)]);
+    }
+
+    #[test]
+    fn a_cover_is_spread_over_the_venues_holding_the_short() {
+        let mut router = router();
+        router.on_fill(&fill("SellShort", 60, "XNYS"));
+        router.on_fill(&fill("SellShort", 40, "BATS"));
+        router.on_fill(&fill("Buy", 50, "BATS"));
+
+        let children = router.split(&order("BuyToCover", "Market", 100, 0.0));
+
+        assert_eq!(venues(&children), vec![("BATS", 40), ("XNYS", 60)]);
+    }
+
+    #[test]
+    fn a_sale_with_no_position_held_on_any_venue_goes_to_the_primary_venue() {
+        let mut router = router();
+        router.on_fill(&fill("Buy", 100, "BATS"));
+        router.on_fill(&fill("Sell", 100, "BATS"));
+
+        let children = router.split(&order("Sell", "Market", 100, 0.0));
+
+        assert_eq!(venues(&children), vec![("XNYS", 100)]);
+    }
+}
@@ -0,0 +1,737 @@
+use crate::algos::AlgoParams;
+use crate::order_groups::LinkedPrices;
+use crate::market_data::{ExecutionReport, MarketNews, Quote, TradePrint};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::time::{Duration, Instant};
+
+// Default location of the automated client population
+pub const STRATEGIES_PATH: &str = "config/strategies.json";
+
+// An order a strategy wants sent; the runner assigns the order id and routes it through a broker
+#[derive(Debug, Clone)]
+pub struct OrderRequest {
+    pub stock: String,
+    pub action: String,     // "Buy", "Sell", "SellShort" or "BuyToCover"
+    pub quantity: u32,
+    pub order_type: String, // "Market", "Limit", an algo ("TWAP", "VWAP", "Iceberg", "POV") or a group ("OCO", "Bracket")
+    pub price: f64,         // Limit price; the current mark for market orders
+    pub time_in_force: String,
+    pub algo: Option<AlgoParams>, // How the broker works an algo order
+    pub links: Option<LinkedPrices>, // Exit prices of an OCO pair or a bracket
+}
+
+// What the trader's feeds hand to the strategies
+#[derive(Debug, Clone)]
+pub enum MarketEvent {
+    Quote(String, Quote),
+    Trade(TradePrint),
+    Execution(ExecutionReport),
+    News(MarketNews),
+}
+
+#[derive(Debug, Clone)]
+pub enum Signal {
+    Submit(OrderRequest),
+    Cancel { order_id: u32, stock: String },
+}
+
+// A trading client driven by market data and its own fills
+pub trait Strategy: Send {
+    fn name(&self) -> &str;
+
+    // Every stock update, with the symbol's latest quote
+    fn on_quote(&mut self, _stock: &str, _quote: &Quote) -> Vec<Signal> {
+        Vec::new()
+    }
+
+    // Every print on the trade tape
+    fn on_trade(&mut self, _trade: &TradePrint) -> Vec<Signal> {
+        Vec::new()
+    }
+
+    // Market-wide events as the stock system applies them
+    fn on_news(&mut self, _news: &MarketNews) -> Vec<Signal> {
+        Vec::new()
+    }
+
+    // Acceptances, rejections, fills and expiries of the strategy's own orders
+    fn on_execution(&mut self, _report: &ExecutionReport) -> Vec<Signal> {
+        Vec::new()
+    }
+
+    // Once a second, with every symbol's latest quote
+    fn on_timer(&mut self, _quotes: &HashMap<String, Quote>) -> Vec<Signal> {
+        Vec::new()
+    }
+}
+
+// Every automated client with the strategy it trades with
+pub type Population = Vec<(u32, Box<dyn Strategy>)>;
+
+// Hand an event to every strategy that should see it, collecting what they send back
+pub fn dispatch(population: &mut Population, event: &MarketEvent) -> Vec<(u32, Signal)> {
+    let mut signals = Vec::new();
+    for (client_id, strategy) in population.iter_mut() {
+        let emitted = match event {
+            MarketEvent::Quote(stock, quote) => strategy.on_quote(stock, quote),
+            MarketEvent::Trade(trade) => strategy.on_trade(trade),
+            MarketEvent::News(news) => strategy.on_news(news),
+            // Each strategy only hears about its own orders
+            MarketEvent::Execution(report) if report.client_id == *client_id => strategy.on_execution(report),
+            MarketEvent::Execution(_) => Vec::new(),
+        };
+        signals.extend(emitted.into_iter().map(|signal| (*client_id, signal)));
+    }
+    signals
+}
+
+// A strategy's own positions and working orders, kept up to date from its execution reports
+#[derive(Debug, Default)]
+pub struct OwnOrders {
+    positions: HashMap<String, i64>,
+    working: BTreeMap<u32, (String, u32)>, // Accepted orders not yet done, with their open quantity
+    unacknowledged: u32,                    // Sent but neither accepted nor rejected yet
+    acknowledged: HashSet<u32>,             // Orders accepted or rejected on at least one venue
+}
+
+impl OwnOrders {
+    pub fn on_execution(&mut self, report: &ExecutionReport) {
+        // An order split across venues is accepted or rejected once per venue, but only sent once
+        if matches!(report.status.as_str(), "Accepted" | "Rejected") && self.acknowledged.insert(report.order_id) {
+            self.unacknowledged = self.unacknowledged.saturating_sub(1);
+        }
+        match report.status.as_str() {
+            "Accepted" => {
+                let (_, open) = self.working.entry(report.order_id).or_insert_with(|| (report.stock.clone(), 0));
+                *open += report.quantity;
+            }
+            "Rejected" => {}
+            "Filled" => {
+                let signed = if matches!(report.action.as_str(), "Buy" | "BuyToCover") {
+                    report.quantity as i64
+                } else {
+                    -(report.quantity as i64)
+                };
+                let position = self.positions.entry(report.stock.clone()).or_insert(0);
+                *position += signed;
+
+                if let Some((_, open)) = self.working.get_mut(&report.order_id) {
+                    *open = open.saturating_sub(report.quantity);
+                    if *open == 0 {
+                        self.working.remove(&report.order_id);
+                    }
+                }
+            }
+            // Whatever is left of the order on that venue will not trade
+            _ => {
+                if let Some((_, open)) = self.working.get_mut(&report.order_id) {
+                    *open = open.saturating_sub(report.quantity);
+                    if *open == 0 {
+                        self.working.remove(&report.order_id);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn position(&self, stock: &str) -> i64 {
+        self.positions.get(stock).copied().unwrap_or(0)
+    }
+
+    // Nothing in flight that could still change the position
+    pub fn is_idle(&self) -> bool {
+        self.unacknowledged == 0 && self.working.is_empty()
+    }
+
+    pub fn cancel_all(&self) -> Vec<Signal> {
+        self.working
+            .iter()
+            .map(|(order_id, (stock, _))| Signal::Cancel { order_id: *order_id, stock: stock.clone() })
+            .collect()
+    }
+
+    // Orders that take the position in `stock` from where it is to `target`: a long position is sold
+    // before going short and a short one covered before going long
+    pub fn trade_to(&mut self, stock: &str, target: i64, order_type: &str, price: f64) -> Vec<Signal> {
+        let position = self.position(stock);
+        let mut legs = Vec::new();
+        if target > position {
+            let cover = (-position).clamp(0, target - position);
+            legs.push(("BuyToCover", cover));
+            legs.push(("Buy", target - position - cover));
+        } else if target < position {
+            let sell = position.clamp(0, position - target);
+            legs.push(("Sell", sell));
+            legs.push(("SellShort", position - target - sell));
+        }
+
+        legs.into_iter()
+            .filter(|(_, quantity)| *quantity > 0)
+            .map(|(action, quantity)| self.submit(stock, action, quantity as u32, order_type, price))
+            .collect()
+    }
+
+    // A day order, counted as in flight until the stock system acknowledges it
+    pub fn submit(&mut self, stock: &str, action: &str, quantity: u32, order_type: &str, price: f64) -> Signal {
+        self.unacknowledged += 1;
+        Signal::Submit(OrderRequest {
+            stock: stock.to_string(),
+            action: action.to_string(),
+            quantity,
+            order_type: order_type.to_string(),
+            price,
+            time_in_force: "Day".to_string(),
+            algo: None,
+            links: None,
+        })
+    }
+
+    // A day order the broker works with an execution algorithm
+    pub fn submit_algo(
+        &mut self,
+        stock: &str,
+        action: &str,
+        quantity: u32,
+        algo: &str,
+        price: f64,
+        params: AlgoParams,
+    ) -> Signal {
+        self.unacknowledged += 1;
+        Signal::Submit(OrderRequest {
+            stock: stock.to_string(),
+            action: action.to_string(),
+            quantity,
+            order_type: algo.to_string(),
+            price,
+            time_in_force: "Day".to_string(),
+            algo: Some(params),
+            links: None,
+        })
+    }
+
+    // A day order whose exits the broker links: an "OCO" pair or a "Bracket" around an entry at `price`
+    pub fn submit_linked(
+        &mut self,
+        stock: &str,
+        action: &str,
+        quantity: u32,
+        group: &str,
+        price: f64,
+        links: LinkedPrices,
+    ) -> Signal {
+        self.unacknowledged += 1;
+        Signal::Submit(OrderRequest {
+            stock: stock.to_string(),
+            action: action.to_string(),
+            quantity,
+            order_type: group.to_string(),
+            price,
+            time_in_force: "Day".to_string(),
+            algo: None,
+            links: Some(links),
+        })
+    }
+}
+
+// How often and how much a noise trader trades
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NoiseParams {
+    pub min_delay_secs: u64,
+    pub max_delay_secs: u64,
+    pub max_quantity: u32,
+    pub market_fraction: f64, // Share of orders sent at market; the rest are limits
+    pub limit_offset: f64,    // Largest distance of a limit from the last price, as a fraction of it
+    pub gtc_fraction: f64,    // Share of limit orders that stay on the book across sessions
+}
+
+impl Default for NoiseParams {
+    fn default() -> Self {
+        Self {
+            min_delay_secs: 20,
+            max_delay_secs: 40,
+            max_quantity: 99,
+            market_fraction: 0.5,
+            limit_offset: 0.1,
+            gtc_fraction: 0.2,
+        }
+    }
+}
+
+// Coin-flip orders in a random symbol every few seconds; what every client did before strategies
+pub struct RandomStrategy {
+    own: OwnOrders,
+    params: NoiseParams,
+    next_order: Instant,
+}
+
+impl RandomStrategy {
+    pub fn new(mut params: NoiseParams) -> Self {
+        params.max_delay_secs = params.max_delay_secs.max(params.min_delay_secs);
+        params.max_quantity = params.max_quantity.max(1);
+        let next_order = Instant::now() + random_delay((params.min_delay_secs, params.max_delay_secs));
+        Self { own: OwnOrders::default(), params, next_order }
+    }
+}
+
+impl Strategy for RandomStrategy {
+    fn name(&self) -> &str {
+        "Random"
+    }
+
+    fn on_execution(&mut self, report: &ExecutionReport) -> Vec<Signal> {
+        self.own.on_execution(report);
+        Vec::new()
+    }
+
+    fn on_timer(&mut self, quotes: &HashMap<String, Quote>) -> Vec<Signal> {
+        if Instant::now() < self.next_order || quotes.is_empty() {
+            return Vec::new();
+        }
+        let params = &self.params;
+        self.next_order = Instant::now() + random_delay((params.min_delay_secs, params.max_delay_secs));
+        let mut rng = rand::thread_rng();
+        let max_quantity = params.max_quantity as i64;
+
+        // Pick from the symbols currently quoted, which follow symbol changes
+        let mut symbols: Vec<&String> = quotes.keys().collect();
+        symbols.sort();
+        let stock = symbols[rng.gen_range(0..symbols.len())].clone();
+
+        // Long holders add or sell, short holders add or cover, flat clients buy or sell short
+        let position = self.own.position(&stock);
+        let (action, quantity) = match (position, rng.gen_bool(0.5)) {
+            (held, true) if held > 0 => ("Buy", rng.gen_range(1..=params.max_quantity)),
+            (held, false) if held > 0 => ("Sell", rng.gen_range(1..=held.min(max_quantity) as u32)),
+            (held, true) if held < 0 => ("BuyToCover", rng.gen_range(1..=(-held).min(max_quantity) as u32)),
+            (held, false) if held < 0 => ("SellShort", rng.gen_range(1..=params.max_quantity)),
+            (_, true) => ("Buy", rng.gen_range(1..=params.max_quantity)),
+            (_, false) => ("SellShort", rng.gen_range(1..=params.max_quantity)),
+        };
+
+        // Decide randomly between Market and Limit order types
+        let order_type = if rng.gen_bool(params.market_fraction.clamp(0.0, 1.0)) { "Market" } else { "Limit" };
+
+        // Some limit orders stay on the book across sessions
+        let gtc = order_type == "Limit" && rng.gen_bool(params.gtc_fraction.clamp(0.0, 1.0));
+        let time_in_force = if gtc { "GTC" } else { "Day" };
+
+        let last = quotes.get(&stock).map(|q| q.last).unwrap_or(0.0);
+        let price = if order_type == "Limit" {
+            // For limit orders, a random limit price within the offset of the current price
+            let offset = params.limit_offset.abs();
+            let price_fluctuation = if offset > 0.0 { rng.gen_range(-offset..=offset) } else { 0.0 };
+            ((last * (1.0 + price_fluctuation)) * 100.0).round() / 100.0
+        } else {
+            (last * 100.0).round() / 100.0 // Ensure 2 decimal places
+        };
+
+        self.own.unacknowledged += 1;
+        vec![Signal::Submit(OrderRequest {
+            stock,
+            action: action.to_string(),
+            quantity,
+            order_type: order_type.to_string(),
+            price,
+            time_in_force: time_in_force.to_string(),
+            algo: None,
+            links: None,
+        })]
+    }
+}
+
+fn random_delay((min, max): (u64, u64)) -> Duration {
+    Duration::from_secs(rand::thread_rng().gen_range(min..=max))
+}
+
+// Long while the short moving average is above the long one, short while it is below
+pub struct MovingAverageCrossover {
+    own: OwnOrders,
+    stock: String,
+    short_window: usize,
+    long_window: usize,
+    quantity: u32,
+    prices: VecDeque<f64>,
+}
+
+impl Strategy for MovingAverageCrossover {
+    fn name(&self) -> &str {
+        "MovingAverageCrossover"
+    }
+
+    fn on_execution(&mut self, report: &ExecutionReport) -> Vec<Signal> {
+        self.own.on_execution(report);
+        Vec::new()
+    }
+
+    fn on_quote(&mut self, stock: &str, quote: &Quote) -> Vec<Signal> {
+        if stock != self.stock {
+            return Vec::new();
+        }
+        push_bounded(&mut self.prices, quote.last, self.long_window);
+        if self.prices.len() < self.long_window || !self.own.is_idle() {
+            return Vec::new();
+        }
+
+        let short = mean(self.prices.iter().rev().take(self.short_window));
+        let long = mean(self.prices.iter());
+        let target = if short > long { self.quantity as i64 } else { -(self.quantity as i64) };
+        self.own.trade_to(stock, target, "Market", quote.last)
+    }
+}
+
+// Fades moves away from the recent average with limit orders at the current price, flat once
+// the price is back inside the band
+pub struct MeanReversion {
+    own: OwnOrders,
+    stock: String,
+    window: usize,
+    band: f64, // Distance from the average, as a fraction of it, before trading
+    quantity: u32,
+    prices: VecDeque<f64>,
+    target: i64,
+}
+
+impl Strategy for MeanReversion {
+    fn name(&self) -> &str {
+        "MeanReversion"
+    }
+
+    fn on_execution(&mut self, report: &ExecutionReport) -> Vec<Signal> {
+        self.own.on_execution(report);
+        Vec::new()
+    }
+
+    fn on_quote(&mut self, stock: &str, quote: &Quote) -> Vec<Signal> {
+        if stock != self.stock {
+            return Vec::new();
+        }
+        push_bounded(&mut self.prices, quote.last, self.window);
+        if self.prices.len() < self.window {
+            return Vec::new();
+        }
+
+        let average = mean(self.prices.iter());
+        let target = if quote.last < average * (1.0 - self.band) {
+            self.quantity as i64
+        } else if quote.last > average * (1.0 + self.band) {
+            -(self.quantity as i64)
+        } else {
+            0
+        };
+
+        // A new target replaces whatever is still working for the old one
+        if target != self.target {
+            self.target = target;
+            return self.own.cancel_all();
+        }
+        if !self.own.is_idle() {
+            return Vec::new();
+        }
+        self.own.trade_to(stock, target, "Limit", quote.last)
+    }
+}
+
+// Follows the direction of the last few trades once they have moved the price far enough
+pub struct Momentum {
+    own: OwnOrders,
+    stock: String,
+    lookback: usize,
+    threshold: f64, // Return over the lookback, as a fraction, that counts as a trend
+    quantity: u32,
+    prices: VecDeque<f64>,
+}
+
+impl Strategy for Momentum {
+    fn name(&self) -> &str {
+        "Momentum"
+    }
+
+    fn on_execution(&mut self, report: &ExecutionReport) -> Vec<Signal> {
+        self.own.on_execution(report);
+        Vec::new()
+    }
+
+    fn on_trade(&mut self, trade: &TradePrint) -> Vec<Signal> {
+        if trade.stock != self.stock {
+            return Vec::new();
+        }
+        push_bounded(&mut self.prices, trade.price, self.lookback + 1);
+        if self.prices.len() <= self.lookback || !self.own.is_idle() {
+            return Vec::new();
+        }
+
+        let first = self.prices.front().copied().unwrap_or(trade.price);
+        let change = (trade.price - first) / first;
+        let position = self.own.position(&self.stock);
+        let target = if change > self.threshold {
+            self.quantity as i64
+        } else if change < -self.threshold {
+            -(self.quantity as i64)
+        } else {
+            position
+        };
+        let stock = self.stock.clone();
+        self.own.trade_to(&stock, target, "Market", trade.price)
+    }
+}
+
+// Quotes a bid and an ask around the last price, leaning both away from its inventory as it builds
+// and pulling the side that would take it past its limit
+pub struct MarketMaker {
+    own: OwnOrders,
+    stock: String,
+    spread: f64, // Between bid and ask, as a fraction of the fair value
+    size: u32,
+    max_inventory: i64,
+    skew: f64, // How many spreads the quotes shift at the inventory limit
+    quoted_at: Option<f64>, // Fair value the working quotes were priced from
+    requoting: bool,        // Cancels sent, waiting for the quotes to come out
+}
+
+impl MarketMaker {
+    fn quote(&mut self, fair: f64) -> Vec<Signal> {
+        let inventory = self.own.position(&self.stock);
+        let lean = self.skew * inventory as f64 / self.max_inventory.max(1) as f64;
+        let center = fair * (1.0 - lean * self.spread);
+        let bid = round_cents(center * (1.0 - self.spread / 2.0));
+        let ask = round_cents(center * (1.0 + self.spread / 2.0)).max(bid + 0.01);
+        let stock = self.stock.clone();
+        let mut signals = Vec::new();
+
+        // Buying covers a short first; selling sells what is held before going short
+        let bid_size = (self.max_inventory - inventory).clamp(0, self.size as i64) as u32;
+        if bid_size > 0 {
+            let action = if inventory < 0 { "BuyToCover" } else { "Buy" };
+            let bid_size = if inventory < 0 { bid_size.min((-inventory) as u32) } else { bid_size };
+            signals.push(self.own.submit(&stock, action, bid_size, "Limit", bid));
+        }
+        let ask_size = (self.max_inventory + inventory).clamp(0, self.size as i64) as u32;
+        if ask_size > 0 {
+            let action = if inventory > 0 { "Sell" } else { "SellShort" };
+            let ask_size = if inventory > 0 { ask_size.min(inventory as u32) } else { ask_size };
+            signals.push(self.own.submit(&stock, action, ask_size, "Limit", ask));
+        }
+
+        self.quoted_at = Some(fair);
+        signals
+    }
+
+    fn requote(&mut self) -> Vec<Signal> {
+        if self.requoting {
+            return Vec::new();
+        }
+        self.requoting = true;
+        self.own.cancel_all()
+    }
+}
+
+impl Strategy for MarketMaker {
+    fn name(&self) -> &str {
+        "MarketMaker"
+    }
+
+    // A fill changes the inventory, so the remaining quote is replaced with a re-skewed pair
+    fn on_execution(&mut self, report: &ExecutionReport) -> Vec<Signal> {
+        self.own.on_execution(report);
+        if report.status == "Filled" && !self.own.is_idle() {
+            return self.requote();
+        }
+        Vec::new()
+    }
+
+    fn on_quote(&mut self, stock: &str, quote: &Quote) -> Vec<Signal> {
+        if stock != self.stock {
+            return Vec::new();
+        }
+        if self.own.is_idle() {
+            self.requoting = false;
+            return self.quote(quote.last);
+        }
+
+        // Quotes priced from a stale fair value come out and go back in around the new one
+        let moved = self.quoted_at.map(|at| (quote.last - at).abs() / at > self.spread / 4.0).unwrap_or(true);
+        if moved {
+            return self.requote();
+        }
+        Vec::new()
+    }
+}
+
+pub fn round_cents(price: f64) -> f64 {
+    ((price * 100.0).round() / 100.0).max(0.01)
+}
+
+fn push_bounded(prices: &mut VecDeque<f64>, price: f64, capacity: usize) {
+    prices.push_back(price);
+    while prices.len() > capacity.max(1) {
+        prices.pop_front();
+    }
+}
+
+fn mean<'a>(prices: impl Iterator<Item = &'a f64>) -> f64 {
+    let (sum, count) = prices.fold((0.0, 0), |(sum, count), price| (sum + price, count + 1));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+// One automated client and the strategy it trades with, as configured in the population file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum StrategySpec {
+    Random {
+        client_id: u32,
+        #[serde(flatten)]
+        params: NoiseParams,
+    },
+    MovingAverageCrossover { client_id: u32, stock: String, short_window: usize, long_window: usize, quantity: u32 },
+    MeanReversion { client_id: u32, stock: String, window: usize, band: f64, quantity: u32 },
+    Momentum { client_id: u32, stock: String, lookback: usize, threshold: f64, quantity: u32 },
+    MarketMaker { client_id: u32, stock: String, spread: f64, size: u32, max_inventory: i64, skew: f64 },
+}
+
+impl StrategySpec {
+    pub fn client_id(&self) -> u32 {
+        match self {
+            StrategySpec::Random { client_id, .. }
+            | StrategySpec::MovingAverageCrossover { client_id, .. }
+            | StrategySpec::MeanReversion { client_id, .. }
+            | StrategySpec::Momentum { client_id, .. }
+            | StrategySpec::MarketMaker { client_id, .. } => *client_id,
+        }
+    }
+
+    pub fn build(&self) -> (u32, Box<dyn Strategy>) {
+        match self.clone() {
+            StrategySpec::Random { client_id, params } => (client_id, Box::new(RandomStrategy::new(params))),
+            StrategySpec::MovingAverageCrossover { client_id, stock, short_window, long_window, quantity } => (
+                client_id,
+                Box::new(MovingAverageCrossover {
+                    own: OwnOrders::default(),
+                    stock,
+                    short_window: short_window.max(1),
+                    long_window: long_window.max(short_window).max(1),
+                    quantity,
+                    prices: VecDeque::new(),
+                }),
+            ),
+            StrategySpec::MeanReversion { client_id, stock, window, band, quantity } => (
+                client_id,
+                Box::new(MeanReversion {
+                    own: OwnOrders::default(),
+                    stock,
+                    window: window.max(1),
+                    band,
+                    quantity,
+                    prices: VecDeque::new(),
+                    target: 0,
+                }),
+            ),
+            StrategySpec::Momentum { client_id, stock, lookback, threshold, quantity } => (
+                client_id,
+                Box::new(Momentum {
+                    own: OwnOrders::default(),
+                    stock,
+                    lookback: lookback.max(1),
+                    threshold,
+                    quantity,
+                    prices: VecDeque::new(),
+                }),
+            ),
+            StrategySpec::MarketMaker { client_id, stock, spread, size, max_inventory, skew } => (
+                client_id,
+                Box::new(MarketMaker {
+                    own: OwnOrders::default(),
+                    stock,
+                    spread,
+                    size,
+                    max_inventory,
+                    skew,
+                    quoted_at: None,
+                    requoting: false,
+                }),
+            ),
+        }
+    }
+}
+
+// One of each built-in strategy, plus market makers in a few symbols; the rest of the clients come
+// from the agent mix
+pub fn default_population() -> Vec<StrategySpec> {
+    let mut population = vec![
+        StrategySpec::MovingAverageCrossover {
+            client_id: 1,
+            stock: "AAPL".to_string(),
+            short_window: 3,
+            long_window: 8,
+            quantity: 20,
+        },
+        StrategySpec::MeanReversion { client_id: 2, stock: "TSLA".to_string(), window: 10, band: 0.02, quantity: 10 },
+        StrategySpec::Momentum { client_id: 3, stock: "NVDA".to_string(), lookback: 5, threshold: 0.01, quantity: 10 },
+    ];
+    for (i, stock) in ["AAPL", "TSLA", "NVDA", "MSFT"].iter().enumerate() {
+        population.push(StrategySpec::MarketMaker {
+            client_id: 4 + i as u32,
+            stock: stock.to_string(),
+            spread: 0.01,
+            size: 25,
+            max_inventory: 100,
+            skew: 1.0,
+        });
+    }
+    population
+}
+
+// Load the strategy clients from disk, falling back to the defaults
+pub fn load_population(path: &str) -> Vec<StrategySpec> {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+            println!("[Strategy] Invalid population {}: {}, using defaults", path, err);
+            default_population()
+        }),
+        Err(_) => default_population(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(order_id: u32, status: &str, quantity: u32, venue: &str) -> ExecutionReport {
+        ExecutionReport {
+            order_id,
+            client_id: 7,
+            stock: "AAPL".to_string(),
+            action: "Buy".to_string(),
+            status: status.to_string(),
+            quantity,
+            price: 10.0,
+            reason: None,
+            broker_id: 1,
+            fee: 0.0,
+            liquidity: None,
+            venue: venue.to_string(),
+            session: String::new(),
+            exchange_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn an_order_split_across_venues_is_acknowledged_once() {
+        let mut own = OwnOrders::default();
+        own.submit("AAPL", "Buy", 300, "Limit", 10.0);
+        own.submit("AAPL", "Buy", 100, "Limit", 10.0);
+
+        own.on_execution(&report(1, "Accepted", 200, "XNYS"));
+        own.on_execution(&report(1, "Accepted", 100, "BATS"));
+        own.on_execution(&report(1, "Filled", 300, "XNYS"));
+
+        // The second order is still in flight
+        assert!(!own.is_idle());
+        own.on_execution(&report(2, "Rejected", 100, "XNYS"));
+        assert!(own.is_idle());
+        assert_eq!(own.position("AAPL"), 300);
+    }
+}
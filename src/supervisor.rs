@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// Default location of the restart policies
+pub const SUPERVISOR_PATH: &str = "config/supervisor.json";
+
+// What to do when a supervised component stops
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(tag = "policy")]
+pub enum RestartPolicy {
+    // A failure is final
+    Never,
+    // Restart after `delay_secs`, giving up once `max_restarts` happen within `window_secs`
+    OnFailure { max_restarts: u32, window_secs: u64, delay_secs: u64 },
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy::OnFailure { max_restarts: 5, window_secs: 60, delay_secs: 1 }
+    }
+}
+
+// Restart policy of every component, with overrides by component name
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SupervisorConfig {
+    pub default_policy: RestartPolicy,
+    pub components: HashMap<String, RestartPolicy>,
+}
+
+impl SupervisorConfig {
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                println!("[Supervisor] Invalid config {}: {}, using defaults", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn policy(&self, component: &str) -> RestartPolicy {
+        self.components.get(component).copied().unwrap_or(self.default_policy)
+    }
+}
+
+struct Component {
+    name: String,
+    critical: bool,
+    policy: RestartPolicy,
+    start: Arc<dyn Fn() + Send + Sync>,
+    handle: Option<JoinHandle<()>>,
+    restarts: VecDeque<Instant>,
+    restart_at: Option<Instant>,
+}
+
+// Owns the threads of every component, restarting them when they fail
+pub struct Supervisor {
+    components: Vec<Component>,
+    config: SupervisorConfig,
+}
+
+impl Supervisor {
+    pub fn new(config: SupervisorConfig) -> Self {
+        Self { components: Vec::new(), config }
+    }
+
+    // Start a component under the restart policy configured for its name. `start` runs the component on
+    // the current thread and must be callable again for a restart, so it should clone whatever shared
+    // state it needs.
+    pub fn spawn<F>(&mut self, name: &str, critical: bool, start: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let mut component = Component {
+            name: name.to_string(),
+            critical,
+            policy: self.config.policy(name),
+            start: Arc::new(start),
+            handle: None,
+            restarts: VecDeque::new(),
+            restart_at: None,
+        };
+        launch(&mut component);
+        self.components.push(component);
+    }
+
+    // Look for components that have stopped and restart them per their policy.
+    // Returns the name of a critical component that could not be recovered.
+    pub fn check(&mut self) -> Result<(), String> {
+        let now = Instant::now();
+
+        for component in self.components.iter_mut() {
+            if let Some(restart_at) = component.restart_at {
+                if now >= restart_at {
+                    component.restart_at = None;
+                    println!("[Supervisor] Restarting {}", component.name);
+                    launch(component);
+                }
+                continue;
+            }
+
+            let finished = component.handle.as_ref().map(|h| h.is_finished()).unwrap_or(false);
+            if !finished {
+                continue;
+            }
+
+            let handle = component.handle.take().expect("handle present");
+            match handle.join() {
+                Ok(()) => println!("[Supervisor] {} stopped unexpectedly", component.name),
+                Err(panic) => println!("[Supervisor] {} panicked: {}", component.name, panic_message(&panic)),
+            }
+
+            match component.policy {
+                RestartPolicy::OnFailure { max_restarts, window_secs, delay_secs } => {
+                    let window = Duration::from_secs(window_secs);
+                    while component.restarts.front().is_some_and(|t| now.duration_since(*t) > window) {
+                        component.restarts.pop_front();
+                    }
+                    if (component.restarts.len() as u32) < max_restarts {
+                        component.restarts.push_back(now);
+                        component.restart_at = Some(now + Duration::from_secs(delay_secs));
+                        continue;
+                    }
+                    println!(
+                        "[Supervisor] {} failed {} times within {:?}, giving up",
+                        component.name, max_restarts, window
+                    );
+                }
+                RestartPolicy::Never => println!("[Supervisor] {} is not restarted", component.name),
+            }
+
+            if component.critical {
+                return Err(component.name.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    // Wait for the named components to exit without restarting them.
+    // Returns the names of those still running when the timeout expired.
+    pub fn join(&mut self, names: &[&str], timeout: Duration) -> Vec<String> {
+        let deadline = Instant::now() + timeout;
+        let mut still_running = Vec::new();
+
+        for component in self.components.iter_mut().filter(|c| names.contains(&c.name.as_str())) {
+            component.restart_at = None;
+            let Some(handle) = component.handle.take() else { continue };
+
+            while !handle.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(50));
+            }
+            if !handle.is_finished() {
+                println!("[Supervisor] {} did not stop in time", component.name);
+                still_running.push(component.name.clone());
+                continue;
+            }
+            if let Err(panic) = handle.join() {
+                println!("[Supervisor] {} panicked while stopping: {}", component.name, panic_message(&panic));
+            }
+        }
+
+        still_running
+    }
+
+    // Wait for every remaining component to exit
+    pub fn join_all(&mut self, timeout: Duration) -> Vec<String> {
+        let names: Vec<String> = self.components.iter().map(|c| c.name.clone()).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        self.join(&names, timeout)
+    }
+}
+
+fn launch(component: &mut Component) {
+    let start = Arc::clone(&component.start);
+    let handle = thread::Builder::new()
+        .name(component.name.clone())
+        .spawn(move || start())
+        .expect("Failed to spawn component thread");
+    component.handle = Some(handle);
+}
+
+fn panic_message(panic: &Box<dyn Any + Send>) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
@@ -0,0 +1,130 @@
+use crate::market_data::{DaySummary, TradePrint};
+use std::collections::{HashMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Number of trades kept per symbol for queries
+pub const TAPE_HISTORY: usize = 500;
+
+// Running open/high/low/volume for the current session
+#[derive(Debug, Clone)]
+struct SessionStats {
+    open: Option<f64>,
+    high: f64,
+    low: f64,
+    volume: u64,
+    trades: u64,
+}
+
+// Record of every fill in the stock system
+pub struct TradeTape {
+    next_trade_id: u64,
+    history: HashMap<String, VecDeque<TradePrint>>,
+    unpublished: Vec<TradePrint>,
+    session: HashMap<String, SessionStats>,
+}
+
+impl Default for TradeTape {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TradeTape {
+    pub fn new() -> Self {
+        Self {
+            next_trade_id: 1,
+            history: HashMap::new(),
+            unpublished: Vec::new(),
+            session: HashMap::new(),
+        }
+    }
+
+    // Record a fill and queue it for publication
+    pub fn record(&mut self, stock: &str, price: f64, quantity: u32, aggressor: &str) {
+        let print = TradePrint {
+            trade_id: self.next_trade_id,
+            stock: stock.to_string(),
+            price: (price * 100.0).round() / 100.0,
+            quantity,
+            aggressor: aggressor.to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+        };
+        self.next_trade_id += 1;
+
+        let stats = self.session.entry(stock.to_string()).or_insert(SessionStats {
+            open: None,
+            high: print.price,
+            low: print.price,
+            volume: 0,
+            trades: 0,
+        });
+        stats.open.get_or_insert(print.price);
+        stats.high = stats.high.max(print.price);
+        stats.low = stats.low.min(print.price);
+        stats.volume += quantity as u64;
+        stats.trades += 1;
+
+        let trades = self.history.entry(stock.to_string()).or_default();
+        if trades.len() == TAPE_HISTORY {
+            trades.pop_front();
+        }
+        trades.push_back(print.clone());
+        self.unpublished.push(print);
+    }
+
+    // Prints recorded since the last call
+    pub fn take_unpublished(&mut self) -> Vec<TradePrint> {
+        std::mem::take(&mut self.unpublished)
+    }
+
+    // Last `count` trades in a symbol, oldest first
+    pub fn recent(&self, stock: &str, count: usize) -> Vec<TradePrint> {
+        self.history
+            .get(stock)
+            .map(|trades| trades.iter().skip(trades.len().saturating_sub(count)).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // Start a new trading day; trade ids and history carry on
+    pub fn start_session(&mut self) {
+        self.session.clear();
+    }
+
+    // Keep a symbol's history and statistics under its new name
+    pub fn rename_symbol(&mut self, old: &str, new: &str) {
+        if let Some(trades) = self.history.remove(old) {
+            self.history.insert(new.to_string(), trades);
+        }
+        if let Some(stats) = self.session.remove(old) {
+            self.session.insert(new.to_string(), stats);
+        }
+    }
+
+    // Open/high/low from the session's trades, closing at the official closing price
+    pub fn day_summary(&self, stock: &str, close: f64) -> DaySummary {
+        let close = (close * 100.0).round() / 100.0;
+        match self.session.get(stock) {
+            Some(stats) => DaySummary {
+                stock: stock.to_string(),
+                open: stats.open.unwrap_or(close),
+                high: stats.high.max(close),
+                low: stats.low.min(close),
+                close,
+                volume: stats.volume,
+                trades: stats.trades,
+            },
+            None => DaySummary {
+                stock: stock.to_string(),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 0,
+                trades: 0,
+            },
+        }
+    }
+}
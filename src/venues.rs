@@ -0,0 +1,136 @@
+use crate::fees::FeeSchedules;
+use crate::market_data::{DepthLevel, Quote};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+// Default location of the venue list, shared by the stock system and the trader
+pub const VENUES_PATH: &str = "config/venues.json";
+
+// One exchange venue: a stock system process with its own books, queues, fees and latency
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Venue {
+    pub name: String,
+    #[serde(default)]
+    pub latency_ms: u64, // Delay before each order reaches the venue's books
+    #[serde(default)]
+    pub fees: Option<FeeSchedules>, // Charged instead of the schedules in config/fees.json
+    #[serde(skip)]
+    pub primary: bool, // The first venue listed; it lists the symbols and announces corporate actions and news
+}
+
+impl Venue {
+    // Queue or component name for this venue. The primary venue keeps the plain names, so a single
+    // venue runs exactly as a lone stock system always has.
+    pub fn scoped(&self, name: &str) -> String {
+        if self.primary {
+            name.to_string()
+        } else {
+            format!("{}.{}", name, self.name)
+        }
+    }
+
+    // Fee schedules that apply to fills on this venue
+    pub fn fee_schedules(&self, default: &FeeSchedules) -> FeeSchedules {
+        self.fees.clone().unwrap_or_else(|| default.clone())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct VenueConfig {
+    pub venues: Vec<Venue>,
+}
+
+impl Default for VenueConfig {
+    fn default() -> Self {
+        Self { venues: vec![Venue { name: "MAIN".to_string(), latency_ms: 0, fees: None, primary: true }] }
+    }
+}
+
+impl VenueConfig {
+    // Load the venues from disk, falling back to the single default venue
+    pub fn load(path: &str) -> Self {
+        let mut config: Self = match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                println!("[Venues] Invalid config {}: {}, using defaults", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+        if config.venues.is_empty() {
+            config = Self::default();
+        }
+        for (index, venue) in config.venues.iter_mut().enumerate() {
+            venue.primary = index == 0;
+        }
+        config
+    }
+
+    pub fn primary(&self) -> &Venue {
+        &self.venues[0]
+    }
+
+    // The named venue; an empty name means the primary venue
+    pub fn find(&self, name: &str) -> Option<&Venue> {
+        if name.is_empty() {
+            return Some(self.primary());
+        }
+        self.venues.iter().find(|venue| venue.name == name)
+    }
+}
+
+// Last price and top of book of one venue in one symbol
+#[derive(Debug, Clone, Default)]
+pub struct VenueQuote {
+    pub last: Option<f64>,
+    pub bid: Option<DepthLevel>,
+    pub ask: Option<DepthLevel>,
+}
+
+// Every venue's quote in every symbol, for the consolidated best bid and offer
+#[derive(Debug, Default)]
+pub struct ConsolidatedQuotes {
+    quotes: HashMap<String, BTreeMap<String, VenueQuote>>,
+}
+
+impl ConsolidatedQuotes {
+    pub fn set_last(&mut self, venue: &str, stock: &str, price: f64) {
+        self.entry(venue, stock).last = Some(price);
+    }
+
+    pub fn set_top(&mut self, venue: &str, stock: &str, bid: Option<DepthLevel>, ask: Option<DepthLevel>) {
+        let quote = self.entry(venue, stock);
+        quote.bid = bid;
+        quote.ask = ask;
+    }
+
+    // Highest bid and lowest ask across the venues, around the given last price
+    pub fn best(&self, stock: &str, last: f64) -> Quote {
+        let mut best = Quote::new(last);
+        for quote in self.quotes.get(stock).into_iter().flat_map(|venues| venues.values()) {
+            if let Some(bid) = &quote.bid {
+                best.bid = Some(best.bid.map_or(bid.price, |b| b.max(bid.price)));
+            }
+            if let Some(ask) = &quote.ask {
+                best.ask = Some(best.ask.map_or(ask.price, |a| a.min(ask.price)));
+            }
+        }
+        best
+    }
+
+    pub fn venue(&self, venue: &str, stock: &str) -> VenueQuote {
+        self.quotes.get(stock).and_then(|venues| venues.get(venue)).cloned().unwrap_or_default()
+    }
+
+    // Follow a corporate action's new symbol on every venue
+    pub fn rename_symbol(&mut self, old: &str, new: &str) {
+        if let Some(venues) = self.quotes.remove(old) {
+            self.quotes.insert(new.to_string(), venues);
+        }
+    }
+
+    fn entry(&mut self, venue: &str, stock: &str) -> &mut VenueQuote {
+        self.quotes.entry(stock.to_string()).or_default().entry(venue.to_string()).or_default()
+    }
+}